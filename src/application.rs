@@ -1,51 +1,153 @@
-use std::{borrow::Borrow, cmp::Ordering};
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+};
 
 use crate::{
-    commands::{CommandParser, HelpCommand, OpenCommand, QuitCommand},
-    logs::{LogLevel, Logger},
+    buffer::Buffer,
+    commands::{
+        AboutCommand, CommandParser, DeleteCommand, HelpCommand, LogsCommand, NewCommand,
+        OpenCommand, PaletteCommand, PwdCommand, QuitCommand, RefreshCommand, RenameCommand,
+        SetCommand, TemplateCommand, TerminalCommand, WriteCommand,
+    },
+    logs::{LogFormat, LogLevel, Logger},
     util::{
-        event::{Event, Events},
-        AppEvent, AppMode, Config, NodeType, StatefulList, Status, StatusLevel, Theme,
+        default_icon, default_keybindings,
+        event::{Event, Events, DISABLE_BRACKETED_PASTE, ENABLE_BRACKETED_PASTE},
+        git_branch, key_name, parse_key, parse_switch, shorten_path, Action, AppEvent, AppMode,
+        Config, ExplorerSort, NodeType, Pane, Progress, ResolvedTheme, StatefulList, Status,
+        StatusLevel,
     },
 };
 
-use async_std::channel::{Receiver, Sender, TryRecvError};
+use async_std::channel::{unbounded, Receiver, Sender, TryRecvError};
+use chrono::Local;
+use futures::executor::block_on;
 use std::{
+    env,
     error::Error,
-    io::{self},
+    fs::{self, DirEntry, File},
+    io::{self, BufRead, BufReader, Write},
     ops::IndexMut,
+    panic,
     path::{Path, PathBuf},
+    process::{self, Child, Stdio},
+    sync::{Arc, Mutex, RwLock, Weak},
+    thread,
+    time::SystemTime,
     vec,
 };
-use termion::{event::Key, input::MouseTerminal, raw::IntoRawMode, screen::AlternateScreen};
+use termion::{
+    cursor,
+    event::Key,
+    input::MouseTerminal,
+    raw::IntoRawMode,
+    screen::{AlternateScreen, ToMainScreen},
+};
 use tui::{
-    backend::TermionBackend,
+    backend::{Backend, TermionBackend},
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Wrap},
-    Terminal,
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
+    Frame, Terminal,
 };
-use uuid::Uuid;
+use unicode_width::UnicodeWidthStr;
+
+// Bounds of the explorer width, as percentage of the frame
+const MIN_EXPLORER_WIDTH: u16 = 10;
+const MAX_EXPLORER_WIDTH: u16 = 60;
+
+// Ticks (250ms each) after which an incomplete leader sequence is cleared
+const LEADER_TIMEOUT_TICKS: usize = 4;
+
+// Largest count that can be typed before a normal mode key, bigger counts are clamped
+const MAX_COUNT: usize = 999;
+
+// Lines of output kept in the terminal pane
+const MAX_TERMINAL_LINES: usize = 1000;
+
+// Events of the commands handled between two frames, so that a busy channel cannot block the interface
+const MAX_EVENTS_PER_FRAME: usize = 64;
+
+// Number of log lines shown by the logs dialog
+const LOGS_DIALOG_LINES: usize = 50;
+
+// Frames of the spinner shown while an operation without a known length is running
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 // Main app state
 pub struct App {
     items: StatefulList<Node>,
     file_view: bool,
+    buffer: Buffer,
+    explorer_width: u16,
     events: Events,
     should_close: bool,
     mode: AppMode,
+    focus: Pane,
     pub command_buffer: String,
     pub command_parser: CommandParser,
     pub status: Status,
     receiver: Receiver<AppEvent>,
     show_dialog: bool,
+    dialog_return_mode: Option<AppMode>,
+    show_palette: bool,
+    palette: StatefulList<(String, String)>,
+    palette_filter: String,
     dialog_content: String,
     dialog_title: String,
+    dialog_scroll: u16,
+    dialog_scroll_to_end: bool,
+    pending_action: Option<AppEvent>,
+    pending_count: Option<usize>,
+    leader_sequence: Option<String>,
+    leader_ticks: usize,
+    keybindings: HashMap<Key, Action>,
+    progress: Option<Progress>,
+    terminal: Option<TerminalPane>,
+    spinner_frame: usize,
+    transmitter: Sender<AppEvent>,
     pub working_path: Option<String>,
+    branch: Option<String>,
     file_list: Nodes,
+    // Path of the open file shared with the commands that need it, updated before running a command
+    current_file: Arc<RwLock<Option<PathBuf>>>,
     logger: Logger,
     config: Config,
+    global_config: Config,
+    colors: ResolvedTheme,
+}
+
+// Output of the command running in the terminal pane, `exit` is set when the command is done
+struct TerminalPane {
+    command: String,
+    // Filled by the threads reading the output of the command, only the last lines are kept
+    lines: Arc<Mutex<VecDeque<String>>>,
+    child: Option<Child>,
+    exit: Option<Option<i32>>,
+}
+
+// Read the lines of a pipe into the terminal pane until the pipe or the pane is closed
+fn read_terminal_output<R: io::Read + Send + 'static>(
+    pipe: R,
+    lines: Weak<Mutex<VecDeque<String>>>,
+) {
+    thread::spawn(move || {
+        for line in BufReader::new(pipe).lines().flatten() {
+            // Once the pane is closed, dropping the pipe stops whatever still writes to it
+            let lines = match lines.upgrade() {
+                Some(lines) => lines,
+                None => break,
+            };
+            let mut lines = lines.lock().unwrap();
+            lines.push_back(line);
+            if lines.len() > MAX_TERMINAL_LINES {
+                lines.pop_front();
+            }
+        }
+    });
 }
 
 #[derive(Clone, Debug)]
@@ -54,9 +156,13 @@ struct Node {
     value: String,
     children: Option<Vec<Box<Node>>>,
     expanded: Option<bool>,
-    uuid: uuid::Uuid,
     layer: u32,
     node_type: NodeType,
+    #[allow(dead_code)]
+    size: Option<u64>,
+    modified: Option<SystemTime>,
+    is_symlink: bool,
+    link_target: Option<PathBuf>,
 }
 
 // Node object, a node is an entry for the explorer that can have children
@@ -74,67 +180,48 @@ impl Node {
             value,
             children,
             expanded,
-            uuid: Uuid::new_v4(),
             layer,
             node_type,
+            size: None,
+            modified: None,
+            is_symlink: false,
+            link_target: None,
         }
     }
 
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.display_name.starts_with('.') {
-            if other.display_name.starts_with('.') {
-                if let NodeType::Directory = self.node_type {
-                    if let NodeType::Directory = other.node_type {
-                        return self.display_name.cmp(&other.display_name);
-                    } else {
-                        return Ordering::Greater;
-                    }
-                } else if let NodeType::File = self.node_type {
-                    if let NodeType::File = other.node_type {
-                        return self.display_name.cmp(&other.display_name);
-                    } else {
-                        return Ordering::Less;
-                    }
-                }
-                return Ordering::Equal;
-            } else {
-                return Ordering::Greater;
-            }
-        }
-
-        if let NodeType::Info = self.node_type {
-            if let NodeType::Info = other.node_type {
-                return self.display_name.cmp(&other.display_name);
-            } else {
-                return Ordering::Greater;
-            }
+    // Compare two nodes with the given sorting, info nodes always go at the end
+    fn cmp_by(&self, other: &Self, sort: ExplorerSort, directories_first: bool) -> Ordering {
+        match (self.node_type, other.node_type) {
+            (NodeType::Info, NodeType::Info) => return self.display_name.cmp(&other.display_name),
+            (NodeType::Info, _) => return Ordering::Greater,
+            (_, NodeType::Info) => return Ordering::Less,
+            _ => {}
         }
 
-        if let NodeType::Directory = self.node_type {
-            if let NodeType::Directory = other.node_type {
-                return self.display_name.cmp(&other.display_name);
-            } else if let NodeType::Info = other.node_type {
-                return Ordering::Less;
-            } else if let NodeType::File = other.node_type {
-                return Ordering::Greater;
+        if directories_first {
+            match (self.node_type, other.node_type) {
+                (NodeType::Directory, NodeType::File) => return Ordering::Less,
+                (NodeType::File, NodeType::Directory) => return Ordering::Greater,
+                _ => {}
             }
         }
 
-        if let NodeType::File = self.node_type {
-            if let NodeType::Directory = other.node_type {
-                return Ordering::Less;
-            } else if let NodeType::Info = other.node_type {
-                return Ordering::Less;
-            } else if let NodeType::File = other.node_type {
-                return self.display_name.cmp(&other.display_name);
-            }
+        match sort {
+            ExplorerSort::Name => self.display_name.cmp(&other.display_name),
+            ExplorerSort::Type => Path::new(&self.display_name)
+                .extension()
+                .cmp(&Path::new(&other.display_name).extension())
+                .then_with(|| self.display_name.cmp(&other.display_name)),
+            // The most recently modified nodes come first
+            ExplorerSort::Modified => other
+                .modified
+                .cmp(&self.modified)
+                .then_with(|| self.display_name.cmp(&other.display_name)),
         }
-
-        Ordering::Equal
     }
 }
 
-// Group of nodes, it can be used to find nodes by their UUID
+// Group of nodes, it can be used to find nodes by their path
 struct Nodes {
     nodes: Vec<Node>,
 }
@@ -144,15 +231,15 @@ impl Nodes {
         Nodes { nodes }
     }
 
-    // Get node from the group by its UUID
-    fn from_uuid(&mut self, uuid: &Uuid) -> Option<&mut Node> {
-        fn check(uuid: Uuid, node: &mut Node) -> Option<&mut Node> {
-            if node.uuid == uuid {
+    // Get node from the group by its path
+    fn from_path(&mut self, path: &str) -> Option<&mut Node> {
+        fn check<'a>(path: &str, node: &'a mut Node) -> Option<&'a mut Node> {
+            if node.value == path {
                 return Some(node);
             } else {
                 if let Some(children) = &mut node.children {
                     for child in children.iter_mut() {
-                        if let Some(node) = check(uuid, child) {
+                        if let Some(node) = check(path, child) {
                             return Some(node);
                         }
                     }
@@ -162,13 +249,176 @@ impl Nodes {
         }
 
         for node in self.nodes.iter_mut() {
-            if let Some(nd) = check(uuid.clone(), node) {
+            if let Some(nd) = check(path, node) {
                 return Some(nd);
             }
         }
 
         None
     }
+
+    // Sort the whole tree of the group
+    fn sort(&mut self, sort: ExplorerSort, directories_first: bool) {
+        fn sort_children(node: &mut Node, sort: ExplorerSort, directories_first: bool) {
+            if let Some(children) = &mut node.children {
+                children.sort_by(|a, b| a.cmp_by(b, sort, directories_first));
+                for child in children.iter_mut() {
+                    sort_children(child, sort, directories_first);
+                }
+            }
+        }
+
+        self.nodes
+            .sort_by(|a, b| a.cmp_by(b, sort, directories_first));
+        for node in self.nodes.iter_mut() {
+            sort_children(node, sort, directories_first);
+        }
+    }
+
+    // Get the paths of the nodes in the same order they are shown in the explorer
+    fn visible_paths(&self) -> Vec<String> {
+        fn collect(node: &Node, paths: &mut Vec<String>) {
+            paths.push(node.value.clone());
+            if let Some(true) = node.expanded {
+                if let Some(children) = &node.children {
+                    for child in children.iter() {
+                        collect(child, paths);
+                    }
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        for node in self.nodes.iter() {
+            collect(node, &mut paths);
+        }
+
+        paths
+    }
+
+    // Get the paths of all the expanded nodes of the group
+    fn expanded_paths(&self) -> Vec<String> {
+        fn collect(node: &Node, paths: &mut Vec<String>) {
+            if let Some(true) = node.expanded {
+                paths.push(node.value.clone());
+            }
+            if let Some(children) = &node.children {
+                for child in children.iter() {
+                    collect(child, paths);
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        for node in self.nodes.iter() {
+            collect(node, &mut paths);
+        }
+
+        paths
+    }
+}
+
+// Check if a symlinked directory points to itself or to one of its parents
+fn is_loop(link: &Path) -> bool {
+    if let (Ok(target), Some(Ok(parent))) =
+        (fs::canonicalize(link), link.parent().map(fs::canonicalize))
+    {
+        parent.starts_with(target)
+    } else {
+        false
+    }
+}
+
+// Create the node of a directory entry, the children of directories are read only when they are expanded
+fn read_node(entry: DirEntry, level: u32) -> Node {
+    let dir = entry.path();
+    let mut node: Node = Node::new(
+        entry.file_name().to_string_lossy().to_string(),
+        dir.to_string_lossy().to_string(),
+        None,
+        None,
+        level,
+        NodeType::File,
+    );
+    // The file type comes with the directory entry, so the metadata is the only system call
+    node.is_symlink = entry.file_type().map(|t| t.is_symlink()).unwrap_or(false);
+    if node.is_symlink {
+        node.link_target = fs::read_link(&dir).ok();
+    }
+    if let Ok(metadata) = fs::metadata(&dir) {
+        node.size = Some(metadata.len());
+        node.modified = metadata.modified().ok();
+        if metadata.is_dir() {
+            node.node_type = NodeType::Directory;
+            // A symlink that points to one of its parents can't be expanded
+            if !(node.is_symlink && is_loop(&dir)) {
+                node.expanded = Some(false);
+            }
+        }
+    }
+
+    node
+}
+
+// Read the children of a directory, errors are collected in `errors` instead of aborting so an unreadable
+// entry doesn't hide the others
+fn read_children(dir: &Path, level: u32, errors: &mut Vec<String>) -> Vec<Box<Node>> {
+    let mut children = Vec::new();
+    match dir.read_dir() {
+        Ok(entries) => {
+            for entry in entries {
+                match entry {
+                    Ok(en) => children.push(Box::new(read_node(en, level))),
+                    Err(e) => {
+                        errors.push(format!("Cannot read an entry of {}: {}", dir.display(), e))
+                    }
+                }
+            }
+        }
+        // Show why the directory can't be read as its only child
+        Err(e) => {
+            errors.push(format!("Cannot read {}: {}", dir.display(), e));
+            children.push(Box::new(Node::new(
+                if let io::ErrorKind::PermissionDenied = e.kind() {
+                    "permission denied".to_string()
+                } else {
+                    e.to_string()
+                },
+                "".to_string(),
+                None,
+                None,
+                level,
+                NodeType::Info,
+            )));
+        }
+    }
+
+    children
+}
+
+// Whether the usage line of a command description shows arguments, a command without usage is assumed to have some
+fn takes_arguments(description: &str) -> bool {
+    description
+        .lines()
+        .find_map(|line| line.strip_prefix("Usage: "))
+        .map_or(true, |usage| usage.trim().contains(' '))
+}
+
+// Canonicalize the parent of a path and join its name back, the missing parents of a path to create are joined
+// back too. `None` if a missing part or the path itself ends with `..`
+fn canonicalize_parent(path: &Path) -> Option<PathBuf> {
+    let mut names = vec![path.file_name()?];
+    let mut parent = path;
+    loop {
+        parent = match parent.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        if let Ok(canonical) = fs::canonicalize(parent) {
+            return Some(names.iter().rev().fold(canonical, |p, name| p.join(name)));
+        }
+        names.push(parent.file_name()?);
+    }
 }
 
 // Add entry to the explorer by expanding all the nodes
@@ -177,9 +427,22 @@ fn expand(
     items: &mut Vec<ListItem>,
     app_list: &mut StatefulList<Node>,
     config: &Config,
+    colors: &ResolvedTheme,
 ) {
     let mut display_name = node.display_name.to_string();
 
+    // If icons are enabled, put the icon of the node before its name
+    if let Some(true) = config.use_icons {
+        if let Some(icon) = icon_for(&node, config) {
+            display_name = format!("{} {}", icon, display_name);
+        }
+    }
+
+    // Show where symlinks point to
+    if let Some(target) = &node.link_target {
+        display_name = format!("{} → {}", display_name, target.display());
+    }
+
     match node.expanded {
         Some(true) => {
             display_name = format!("▼ {}", display_name);
@@ -202,99 +465,206 @@ fn expand(
 
     app_list.items.push(Node {
         display_name: display_name.to_string(),
-        value: display_name.to_string(),
+        value: node.value.clone(),
         children: None,
         expanded: None,
-        uuid: node.uuid.clone(),
         layer: 0,
-        node_type: NodeType::File,
+        node_type: node.node_type,
+        size: node.size,
+        modified: node.modified,
+        is_symlink: node.is_symlink,
+        link_target: node.link_target.clone(),
     });
 
     items.push(
         ListItem::new(vec![Spans::from(display_name.to_string())]).style(
             Style::default()
-                .fg(if let NodeType::Directory = node.node_type {
+                .fg(if node.is_symlink {
+                    colors.explorer_symlink_foreground
+                } else if let NodeType::Directory = node.node_type {
                     if node.display_name.starts_with('.') {
-                        Theme::get_color_for(if let Some(theme) = config.theme.clone() {
-                            if let Some(k) = theme.explorer_hidden_foreground {
-                                Some(k)
-                            } else {
-                                Some(Theme::default().explorer_hidden_foreground.unwrap())
-                            }
-                        } else {
-                            Some(Theme::default().explorer_hidden_foreground.unwrap())
-                        })
-                        .unwrap()
+                        colors.explorer_hidden_foreground
                     } else {
-                        Theme::get_color_for(if let Some(theme) = config.theme.clone() {
-                            if let Some(k) = theme.explorer_directory_foreground {
-                                Some(k)
-                            } else {
-                                Some(Theme::default().explorer_directory_foreground.unwrap())
-                            }
-                        } else {
-                            Some(Theme::default().explorer_directory_foreground.unwrap())
-                        })
-                        .unwrap()
+                        colors.explorer_directory_foreground
                     }
                 } else if let NodeType::Info = node.node_type {
-                    Theme::get_color_for(if let Some(theme) = config.theme.clone() {
-                        if let Some(k) = theme.explorer_info_foreground {
-                            Some(k)
-                        } else {
-                            Some(Theme::default().explorer_info_foreground.unwrap())
-                        }
-                    } else {
-                        Some(Theme::default().explorer_info_foreground.unwrap())
-                    })
-                    .unwrap()
+                    colors.explorer_info_foreground
                 } else {
                     if node.display_name.starts_with('.') {
-                        Theme::get_color_for(if let Some(theme) = config.theme.clone() {
-                            if let Some(k) = theme.explorer_hidden_foreground {
-                                Some(k)
-                            } else {
-                                Some(Theme::default().explorer_hidden_foreground.unwrap())
-                            }
-                        } else {
-                            Some(Theme::default().explorer_hidden_foreground.unwrap())
-                        })
-                        .unwrap()
+                        colors.explorer_hidden_foreground
                     } else {
-                        Theme::get_color_for(if let Some(theme) = config.theme.clone() {
-                            if let Some(k) = theme.explorer_file_foreground {
-                                Some(k)
-                            } else {
-                                Some(Theme::default().explorer_file_foreground.unwrap())
-                            }
-                        } else {
-                            Some(Theme::default().explorer_file_foreground.unwrap())
-                        })
-                        .unwrap()
+                        colors.explorer_file_foreground
                     }
                 })
-                .bg(
-                    Theme::get_color_for(if let Some(theme) = config.theme.clone() {
-                        if let Some(k) = theme.app_background {
-                            Some(k)
-                        } else {
-                            Some(Theme::default().app_background.unwrap())
-                        }
-                    } else {
-                        Some(Theme::default().app_background.unwrap())
-                    })
-                    .unwrap(),
-                ),
+                .bg(colors.app_background),
         ),
     );
 
     if let Some(true) = node.expanded {
         if let Some(children) = node.children.clone() {
             for child in children.iter() {
-                expand(*child.clone(), items, app_list, config);
+                expand(*child.clone(), items, app_list, config, colors);
+            }
+        }
+    }
+}
+
+// Get the icon of the given node, the icons from the configuration take precedence over the default ones
+fn icon_for(node: &Node, config: &Config) -> Option<String> {
+    let lookup = |key: &str| {
+        if let Some(icons) = &config.icons {
+            if let Some(icon) = icons.get(key) {
+                return Some(icon.clone());
+            }
+        }
+        default_icon(key).map(String::from)
+    };
+
+    match node.node_type {
+        NodeType::Directory => {
+            if let Some(true) = node.expanded {
+                lookup("directory_open")
+            } else {
+                lookup("directory")
             }
         }
+        NodeType::File => {
+            if let Some(extension) = Path::new(&node.display_name).extension() {
+                if let Some(icon) = lookup(&*extension.to_string_lossy()) {
+                    return Some(icon);
+                }
+            }
+            lookup("file")
+        }
+        NodeType::Info => None,
+    }
+}
+
+// Names of the keys bound to the given actions, in the order of the actions
+fn bound_keys(keybindings: &HashMap<Key, Action>, actions: &[Action]) -> Vec<String> {
+    let mut keys = Vec::new();
+    for action in actions {
+        let mut names: Vec<String> = keybindings
+            .iter()
+            .filter(|(_, a)| **a == *action)
+            .map(|(k, _)| key_name(*k))
+            .collect();
+        names.sort();
+        for name in names {
+            if !keys.contains(&name) {
+                keys.push(name);
+            }
+        }
+    }
+    keys
+}
+
+// Hint shown under a dialog with the keys that answer it
+fn dialog_hint(keybindings: &HashMap<Key, Action>, confirm: bool) -> String {
+    // Wrap the names and join them like `<a>, <b> or <c>`
+    let choices = |actions: &[Action]| {
+        let mut keys: Vec<String> = bound_keys(keybindings, actions)
+            .iter()
+            .map(|k| format!("<{}>", k))
+            .collect();
+        match keys.pop() {
+            Some(last) if keys.is_empty() => last,
+            Some(last) => format!("{} or {}", keys.join(", "), last),
+            None => "an unbound key".to_string(),
+        }
+    };
+
+    if confirm {
+        format!(
+            "Press {} to confirm, {} to cancel",
+            choices(&[Action::Confirm]),
+            choices(&[Action::Cancel, Action::Close, Action::Quit])
+        )
+    } else {
+        format!(
+            "Press {} to close",
+            choices(&[Action::Open, Action::Close, Action::Quit])
+        )
+    }
+}
+
+// Lines of the start screen shown in the editor when no workspace is open, with the configured keys
+fn welcome_screen(keybindings: &HashMap<Key, Action>) -> Vec<Spans<'static>> {
+    let keys_for = |action: Action| {
+        let names = bound_keys(keybindings, &[action]);
+        if names.is_empty() {
+            "unbound".to_string()
+        } else {
+            names.join(", ")
+        }
+    };
+    let bindings = [
+        (keys_for(Action::Quit), "Quit LEdit"),
+        (keys_for(Action::ToggleExplorer), "Open/close the explorer"),
+        (keys_for(Action::CommandMode), "Enter command mode"),
+        (keys_for(Action::InsertMode), "Enter insert mode"),
+        // Going back to normal mode isn't configurable
+        ("Esc".to_string(), "Go back to normal mode"),
+        (
+            format!("{}/{}", keys_for(Action::Up), keys_for(Action::Down)),
+            "Move in the explorer",
+        ),
+        (keys_for(Action::ExpandNode), "Expand/collapse a directory"),
+    ];
+
+    let mut lines = vec![
+        Spans::from(""),
+        Spans::from(Span::styled(
+            "Welcome to LEdit",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Spans::from(""),
+        Spans::from("Open a workspace with `open <directory>` in command mode"),
+        Spans::from("or start LEdit with `ledit <directory>`"),
+        Spans::from(""),
+        Spans::from(Span::styled(
+            "Key bindings",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+    ];
+
+    for (key, description) in bindings.iter() {
+        lines.push(Spans::from(format!("{:>8}  {:<28}", key, description)));
+    }
+
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(
+        "Type `help <command>` in command mode for more",
+    ));
+
+    lines
+}
+
+// Build the status line from the given format, replacing the known `{placeholder}`s with their values
+// and keeping the unknown ones as they are
+fn format_status<'a>(format: &str, placeholders: &HashMap<&str, Span<'a>>) -> Spans<'a> {
+    let mut spans = Vec::new();
+    let mut rest = format;
+
+    while let Some(start) = rest.find('{') {
+        if let Some(end) = rest[start..].find('}') {
+            let end = start + end;
+            if let Some(value) = placeholders.get(&rest[start + 1..end]) {
+                spans.push(Span::raw(rest[..start].to_string()));
+                spans.push(value.clone());
+                rest = &rest[end + 1..];
+            } else {
+                // Keep the brace and look again after it, a placeholder can follow a lone `{`
+                spans.push(Span::raw(rest[..=start].to_string()));
+                rest = &rest[start + 1..];
+            }
+        } else {
+            break;
+        }
     }
+    spans.push(Span::raw(rest.to_string()));
+
+    Spans::from(spans)
 }
 
 impl App {
@@ -303,683 +673,1259 @@ impl App {
         rx: Receiver<AppEvent>,
         config: Config,
     ) -> Result<App, Box<dyn Error>> {
-        Ok(App {
+        let mut app = App {
             items: StatefulList::new(),
             file_view: true,
+            buffer: Buffer::new(),
+            explorer_width: 0,
             events: Events::new(),
             should_close: false,
             mode: AppMode::NormalMode,
+            focus: Pane::Explorer,
             command_buffer: "".to_string(),
             command_parser: CommandParser::new(tx.clone()),
             status: Status::default(),
             receiver: rx,
             show_dialog: false,
+            dialog_return_mode: None,
+            show_palette: false,
+            palette: StatefulList::new(),
+            palette_filter: String::new(),
             dialog_content: String::new(),
             dialog_title: String::new(),
+            dialog_scroll: 0,
+            dialog_scroll_to_end: false,
+            pending_action: None,
+            pending_count: None,
+            leader_sequence: None,
+            leader_ticks: 0,
+            keybindings: HashMap::new(),
+            progress: None,
+            terminal: None,
+            spinner_frame: 0,
+            transmitter: tx.clone(),
             working_path: None,
+            branch: None,
             file_list: Nodes::new(Vec::new()),
-            logger: Logger::new(if let Some(dir) = &config.logs_directory {
-                dir.clone()
-            } else {
-                Config::default().logs_directory.unwrap()
-            }),
+            current_file: Arc::new(RwLock::new(None)),
+            logger: Logger::new(
+                if let Some(dir) = &config.logs_directory {
+                    dir.clone()
+                } else {
+                    Config::default().logs_directory.unwrap()
+                },
+                if let Some(format) = &config.log_format {
+                    LogFormat::from_name(format).unwrap_or(LogFormat::Text)
+                } else {
+                    LogFormat::Text
+                },
+                if let Some(max) = config.max_log_files {
+                    max
+                } else {
+                    Config::default().max_log_files.unwrap()
+                },
+            ),
+            global_config: config.clone(),
+            colors: ResolvedTheme::new(config.theme.clone()),
             config,
-        })
+        };
+        app.apply_config();
+        Ok(app)
     }
 
     pub fn setup_commands(&mut self) {
         self.command_parser.add_command(Box::new(QuitCommand));
         self.command_parser.add_command(Box::new(OpenCommand));
+        self.command_parser
+            .add_command(Box::new(WriteCommand::new(self.current_file.clone())));
+        self.command_parser.add_command(Box::new(SetCommand));
+        self.command_parser.add_command(Box::new(PwdCommand));
+        self.command_parser.add_command(Box::new(RefreshCommand));
+        self.command_parser.add_command(Box::new(AboutCommand));
+        self.command_parser.add_command(Box::new(LogsCommand));
+        self.command_parser.add_command(Box::new(PaletteCommand));
+        self.command_parser.add_command(Box::new(NewCommand));
+        self.command_parser.add_command(Box::new(DeleteCommand));
+        self.command_parser.add_command(Box::new(RenameCommand));
+        self.command_parser.add_command(Box::new(TemplateCommand));
+        self.command_parser.add_command(Box::new(TerminalCommand));
         self.command_parser
             .add_command(Box::new(HelpCommand::new(&self.command_parser.commands)));
     }
 
     pub fn close(&mut self) {
+        self.close_terminal();
         self.should_close = true;
     }
 
-    pub fn load_explorer(&mut self) -> Result<(), Box<dyn Error>> {
-        fn expand_path(dir: PathBuf, level: u32) -> Result<Node, Box<dyn Error>> {
-            let mut node: Node = Node::new(
-                dir.file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string()
-                    .clone(),
-                dir.to_str().unwrap().to_string().clone(),
-                None,
-                None,
-                level,
-                NodeType::Directory,
-            );
-            if dir.exists() {
-                let mut children = Vec::new();
-                if dir.is_dir() {
-                    for entry in dir.read_dir()? {
-                        if let Ok(en) = entry {
-                            if let Ok(child) = expand_path(en.path(), level + 1) {
-                                children.push(Box::new(child));
-                            }
-                        }
-                    }
-                    node.children = Some(children);
-                    node.expanded = Some(false);
-                    node.node_type = NodeType::Directory;
-                } else {
-                    node.node_type = NodeType::File;
-                }
-            }
+    // Show the given message in the status and write it to the logs
+    pub fn notify(&mut self, level: StatusLevel, text: String) {
+        self.logger.log(
+            match level {
+                StatusLevel::INFO => LogLevel::INFO,
+                StatusLevel::WARNING => LogLevel::WARN,
+                StatusLevel::ERROR => LogLevel::ERROR,
+            },
+            text.clone(),
+        );
+        self.status = Status { text, level };
+    }
 
-            Ok(node)
+    // Resolve a path given by the user, `~` is expanded and relative paths are joined to the workspace
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = if let Ok(p) = shellexpand::full(path) {
+            PathBuf::from(&*p)
+        } else {
+            PathBuf::from(path)
+        };
+
+        if path.is_absolute() {
+            return path;
         }
 
-        if let Some(workspace_path) = &self.working_path {
-            let mut expl = Vec::new();
-            let path = Path::new(workspace_path);
-            if path.exists() {
-                if path.is_dir() {
-                    for entry in path.read_dir()? {
-                        if let Ok(en) = entry {
-                            if let Ok(nd) = expand_path(en.path(), 0) {
-                                expl.push(nd.clone());
-                            }
-                        }
+        if let Some(workspace) = &self.working_path {
+            Path::new(workspace).join(path)
+        } else {
+            path
+        }
+    }
+
+    // Update the count typed before a normal mode key and get how many times the key should be repeated,
+    // digits are added to the count (a leading `0` is not) up to `MAX_COUNT` and any other key consumes it
+    fn update_count(&mut self, event: &Event<Key>) -> usize {
+        match event {
+            Event::Input(Key::Char(c)) => {
+                if let (AppMode::NormalMode, Some(digit)) = (&self.mode, c.to_digit(10)) {
+                    if !self.show_dialog
+                        && !self.show_palette
+                        && self.leader_sequence.is_none()
+                        && (digit != 0 || self.pending_count.is_some())
+                    {
+                        self.pending_count = Some(
+                            (self.pending_count.unwrap_or(0) * 10 + digit as usize).min(MAX_COUNT),
+                        );
+                        return 0;
                     }
                 }
+                self.pending_count.take().unwrap_or(1)
             }
-            self.file_list.nodes = expl;
+            Event::Input(_) | Event::Paste(_) => self.pending_count.take().unwrap_or(1),
+            Event::Tick => 1,
+        }
+    }
+
+    // Create a file at the given path from a template, filling its placeholders
+    fn create_from_template(&mut self, name: &str, path: &str) -> Result<PathBuf, String> {
+        let target = self.resolve_in_workspace(path)?;
+        let template = if let Ok(dir) = shellexpand::full("~/.ledit/templates") {
+            PathBuf::from(&*dir).join(name)
         } else {
-            self.file_list.nodes = vec![Node::new(
-                "Empty workspace".to_string(),
-                "".to_string(),
-                None,
-                None,
-                0,
-                NodeType::Info,
-            )];
-            self.logger.log(
-                LogLevel::WARN,
-                "No workspace directory is provided, using empty workspace".to_string(),
-            );
+            return Err("Cannot find the templates directory".to_string());
+        };
+        let content = fs::read_to_string(&template)
+            .map_err(|e| format!("Cannot read the template {}: {}", name, e))?;
+
+        if target.exists() {
+            return Err(format!("{} already exists", self.display_path(&target)));
         }
 
-        self.file_list.nodes.sort_by(|a, b| b.cmp(a));
+        let filename = if let Some(f) = target.file_name() {
+            f.to_string_lossy().to_string()
+        } else {
+            String::new()
+        };
+        let content = content
+            .replace("{{filename}}", &filename)
+            .replace("{{date}}", &Local::now().format("%Y-%m-%d").to_string());
+
+        // Missing parent directories are created too
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Cannot create {}: {}", self.display_path(parent), e))?;
+        }
+        fs::write(&target, content)
+            .map_err(|e| format!("Cannot create {}: {}", self.display_path(&target), e))?;
 
-        Ok(())
+        Ok(target)
     }
-}
 
-// Render method, this is the main loop that renders all the TUI
-pub fn render(app: &mut App) -> Result<(), Box<dyn Error>> {
-    let stdout = io::stdout().into_raw_mode()?;
-    let stdout = MouseTerminal::from(stdout);
-    let stdout = AlternateScreen::from(stdout);
-    let backend = TermionBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // Create an empty file in the workspace, or a directory if the path ends with `/`
+    fn create_path(&mut self, path: &str) -> Result<PathBuf, String> {
+        let target = self.resolve_in_workspace(path)?;
+        if target.exists() {
+            return Err(format!("{} already exists", self.display_path(&target)));
+        }
 
-    app.logger
-        .log(LogLevel::INFO, "Loading the explorer".to_string());
-    if let Err(_) = app.load_explorer() {
-        app.status = Status {
-            text: "Cannot load explorer!".to_string(),
-            level: StatusLevel::ERROR,
+        let result = if path.ends_with('/') {
+            fs::create_dir_all(&target)
+        } else {
+            // Missing parent directories are created too
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Cannot create {}: {}", self.display_path(parent), e))?;
+            }
+            File::create(&target).map(|_| ())
         };
-        app.logger
-            .log(LogLevel::ERROR, "Cannot load the explorer".to_string());
-    } else {
-        app.logger
-            .log(LogLevel::INFO, "Explorer loaded".to_string());
+        result.map_err(|e| format!("Cannot create {}: {}", self.display_path(&target), e))?;
+
+        Ok(target)
     }
 
-    loop {
-        // If the app should close, close it and write the generated logs
-        if app.should_close {
-            app.logger.write();
-            break;
+    // Resolve a path that must be strictly inside the workspace. The parent directory is canonicalized, not the
+    // path itself, so that a symlink is the link and not what it points to
+    fn resolve_in_workspace(&self, path: &str) -> Result<PathBuf, String> {
+        let workspace = match &self.working_path {
+            Some(w) => {
+                fs::canonicalize(w).map_err(|e| format!("Cannot find the workspace: {}", e))?
+            }
+            None => return Err("No workspace is open, use `open <directory>` first".to_string()),
+        };
+
+        match canonicalize_parent(&self.resolve_path(path)) {
+            Some(target) if target.starts_with(&workspace) && target != workspace => Ok(target),
+            Some(_) => Err(format!("{} is not inside the workspace", path)),
+            None => Err(format!("Cannot find {}", path)),
         }
-        terminal
-            .draw(|f| {
-                // Size for the current frame
-                let size = f.size();
-
-                // If a dialog is open, render it
-                if app.show_dialog {
-                    // Block of the dialog
-                    let dialog_block = Block::default()
-                        .title(app.dialog_title.clone())
-                        .border_style(Style::default().fg(Color::Red))
-                        .border_type(BorderType::Rounded)
-                        .borders(Borders::ALL);
-
-                    // Block of the "continue" text
-                    let continue_block = Block::default().borders(Borders::NONE);
-
-                    let dialog_paragraph = Paragraph::new(app.dialog_content.clone())
-                        .block(dialog_block)
-                        .alignment(Alignment::Center);
-
-                    let dialog_chunks = Layout::default()
-                        .constraints([Constraint::Percentage(90), Constraint::Percentage(10)])
-                        .direction(Direction::Vertical)
-                        .split(Rect {
-                            x: (size.x + (size.width / 2)) - (size.width / 2) / 2,
-                            y: (size.y + (size.height / 2)) - (size.height / 2) / 2,
-                            height: size.height / 2,
-                            width: size.width / 2,
-                        });
-
-                    f.render_widget(
-                        dialog_paragraph,
-                        Rect {
-                            x: (size.x + (size.width / 2)) - (size.width / 2) / 2,
-                            y: (size.y + (size.height / 2)) - (size.height / 2) / 2,
-                            height: size.height / 2,
-                            width: size.width / 2,
-                        },
-                    );
-                    f.render_widget(
-                        Paragraph::new("Press <ENTER> to close")
-                            .block(continue_block)
-                            .alignment(Alignment::Center),
-                        dialog_chunks[1],
-                    );
-                }
-                // Main block
-                let block = Block::default()
-                    .title("LEdit")
-                    .border_style(
-                        Style::default().fg(Theme::get_color_for(
-                            if let Some(theme) = app.config.theme.clone() {
-                                if let Some(k) = theme.app_foreground {
-                                    Some(k)
-                                } else {
-                                    Some(Theme::default().app_foreground.unwrap())
-                                }
-                            } else {
-                                Some(Theme::default().app_foreground.unwrap())
-                            },
-                        )
-                        .unwrap()),
-                    )
-                    .style(
-                        Style::default().bg(Theme::get_color_for(
-                            if let Some(theme) = app.config.theme.clone() {
-                                if let Some(k) = theme.app_background {
-                                    Some(k)
-                                } else {
-                                    Some(Theme::default().app_background.unwrap())
-                                }
-                            } else {
-                                Some(Theme::default().app_background.unwrap())
-                            },
-                        )
-                        .unwrap()),
-                    )
-                    .border_type(BorderType::Rounded)
-                    .borders(Borders::TOP | Borders::BOTTOM);
-                f.render_widget(block, size);
-
-                let top_chunks: Vec<Rect>;
-                let chunks: Vec<Rect>;
-                let bottom_chunks: Vec<Rect>;
-
-                top_chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Percentage(4), Constraint::Percentage(96)])
-                    .margin(1)
-                    .split(size);
-
-                // If the command view is open set its with to the 20% of the frame and the rest to the 80%
-                if let AppMode::CommandMode = app.mode {
-                    bottom_chunks = Layout::default()
-                        .margin(0)
-                        .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
-                        .direction(Direction::Vertical)
-                        .split(top_chunks[1]);
-                } else {
-                    bottom_chunks = Layout::default()
-                        .margin(0)
-                        .constraints([Constraint::Percentage(100), Constraint::Percentage(0)])
-                        .direction(Direction::Vertical)
-                        .split(top_chunks[1]);
-                }
+    }
 
-                // If the explorer is open set its width to the 20% of the frame and the editor's width to the 80%, else the editor should have a width of 100%
-                if app.file_view {
-                    chunks = Layout::default()
-                        .margin(1)
-                        .constraints(
-                            [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),
-                        )
-                        .direction(Direction::Horizontal)
-                        .split(bottom_chunks[0]);
-                } else {
-                    chunks = Layout::default()
-                        .margin(1)
-                        .constraints(
-                            [Constraint::Percentage(0), Constraint::Percentage(100)].as_ref(),
-                        )
-                        .direction(Direction::Horizontal)
-                        .split(bottom_chunks[0]);
+    // Path of the file open in the editor, canonicalized like the paths of the workspace operations
+    fn open_path(&self) -> Option<PathBuf> {
+        self.buffer.path.as_deref().and_then(canonicalize_parent)
+    }
+
+    // Delete a file or a whole directory of the workspace
+    fn delete_path(&mut self, path: &str) -> Result<PathBuf, String> {
+        let target = self.resolve_in_workspace(path)?;
+        // The open file is detached from the deleted path, so that writing it doesn't recreate it
+        let detach = self.open_path().map_or(false, |p| p.starts_with(&target));
+
+        // A symlink is removed without touching what it points to
+        let metadata = fs::symlink_metadata(&target)
+            .map_err(|e| format!("Cannot delete {}: {}", self.display_path(&target), e))?;
+        let result = if metadata.is_dir() {
+            fs::remove_dir_all(&target)
+        } else {
+            fs::remove_file(&target)
+        };
+        result.map_err(|e| format!("Cannot delete {}: {}", self.display_path(&target), e))?;
+
+        if detach {
+            self.buffer.path = None;
+            self.buffer.dirty = true;
+        }
+
+        Ok(target)
+    }
+
+    // Rename or move a file or a directory of the workspace, the open file follows it
+    fn rename_path(&mut self, old: &str, new: &str) -> Result<(PathBuf, PathBuf), String> {
+        let from = self.resolve_in_workspace(old)?;
+        let to = self.resolve_in_workspace(new)?;
+        if to.exists() {
+            return Err(format!("{} already exists", self.display_path(&to)));
+        }
+        let open = self.open_path();
+        fs::rename(&from, &to)
+            .map_err(|e| format!("Cannot rename {}: {}", self.display_path(&from), e))?;
+
+        if let Some(path) = open {
+            if let Ok(rest) = path.strip_prefix(&from) {
+                self.buffer.path = Some(if rest.as_os_str().is_empty() {
+                    to.clone()
+                } else {
+                    to.join(rest)
+                });
+            }
+        }
+
+        Ok((from, to))
+    }
+
+    // Shorten a path for the status and the messages
+    fn display_path(&self, path: &Path) -> String {
+        shorten_path(
+            path,
+            if let Some(w) = self.config.max_path_width {
+                w
+            } else {
+                Config::default().max_path_width.unwrap()
+            },
+        )
+    }
+
+    // If the workspace has been deleted or unmounted, go back to the empty workspace state
+    fn check_workspace(&mut self) {
+        let workspace = match &self.working_path {
+            Some(w) => w.clone(),
+            None => return,
+        };
+
+        if !Path::new(&workspace).is_dir() {
+            self.working_path = None;
+            self.file_list = Nodes::new(Vec::new());
+            self.items = StatefulList::new();
+            // Without a workspace the explorer only shows that it is empty, this can't fail
+            let _ = self.load_explorer();
+            self.notify(
+                StatusLevel::WARNING,
+                format!(
+                    "The workspace {} doesn't exist anymore, use `open <directory>` to open another one",
+                    self.display_path(Path::new(&workspace))
+                ),
+            );
+        }
+    }
+
+    // Recreate the channel used by the commands to send events to the app
+    fn reconnect_events(&mut self) {
+        let (tx, rx) = unbounded();
+        self.receiver = rx;
+        self.transmitter = tx.clone();
+        self.command_parser.set_transmitter(tx);
+        self.notify(
+            StatusLevel::ERROR,
+            "The application events channel was closed and has been reopened".to_string(),
+        );
+    }
+
+    // Run a shell command in the workspace, its output is read off-thread into the terminal pane
+    fn run_in_terminal(&mut self, command: String) {
+        // Only one command runs at a time, the previous one is stopped
+        self.close_terminal();
+
+        let mut shell = process::Command::new("sh");
+        shell
+            .arg("-c")
+            .arg(&command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(dir) = &self.working_path {
+            shell.current_dir(dir);
+        }
+
+        let lines = Arc::new(Mutex::new(VecDeque::new()));
+        let (child, exit) = match shell.spawn() {
+            Ok(mut child) => {
+                // Each pipe is read on its own thread so that neither can fill up
+                if let Some(stdout) = child.stdout.take() {
+                    read_terminal_output(stdout, Arc::downgrade(&lines));
                 }
+                if let Some(stderr) = child.stderr.take() {
+                    read_terminal_output(stderr, Arc::downgrade(&lines));
+                }
+                self.progress = Some(Progress {
+                    label: format!("Running {}", command),
+                    done: None,
+                });
+                self.spinner_frame = 0;
+                (Some(child), None)
+            }
+            Err(e) => {
+                lines
+                    .lock()
+                    .unwrap()
+                    .push_back(format!("Cannot run the command: {}", e));
+                (None, Some(None))
+            }
+        };
 
-                // If the explorer is open, render it
-                if app.file_view {
-                    let files = Block::default()
-                        .border_style(Style::default().fg(if let AppMode::NormalMode = app.mode {
-                            Theme::get_color_for(if let Some(theme) = app.config.theme.clone() {
-                                if let Some(k) = theme.active_view_border {
-                                    Some(k)
-                                } else {
-                                    Some(Theme::default().active_view_border.unwrap())
-                                }
-                            } else {
-                                Some(Theme::default().active_view_border.unwrap())
-                            })
-                            .unwrap()
-                        } else {
-                            Theme::get_color_for(if let Some(theme) = app.config.theme.clone() {
-                                if let Some(k) = theme.view_border {
-                                    Some(k)
-                                } else {
-                                    Some(Theme::default().view_border.unwrap())
-                                }
-                            } else {
-                                Some(Theme::default().view_border.unwrap())
-                            })
-                            .unwrap()
-                        }))
-                        .borders(Borders::ALL)
-                        .title("Explorer")
-                        .border_type(BorderType::Plain)
-                        .style(
-                            Style::default().bg(Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.explorer_background {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().explorer_background.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().explorer_background.unwrap())
-                                },
-                            )
-                            .unwrap()),
+        self.terminal = Some(TerminalPane {
+            command,
+            lines,
+            child,
+            exit,
+        });
+    }
+
+    // Check if the command of the terminal pane is done, without waiting for it
+    fn poll_terminal(&mut self) {
+        if let Some(terminal) = &mut self.terminal {
+            if let Some(child) = &mut terminal.child {
+                if let Ok(Some(status)) = child.try_wait() {
+                    terminal.exit = Some(status.code());
+                    terminal.child = None;
+                    self.progress = None;
+                }
+            }
+        }
+    }
+
+    // Close the terminal pane, killing its command if it is still running
+    fn close_terminal(&mut self) {
+        if let Some(mut terminal) = self.terminal.take() {
+            if let Some(mut child) = terminal.child.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+                self.progress = None;
+            }
+        }
+    }
+
+    // Show a dialog, the mode is restored when it is closed
+    fn open_dialog(&mut self, title: String, content: String, action: Option<AppEvent>) {
+        if !self.show_dialog {
+            self.dialog_return_mode = Some(std::mem::replace(&mut self.mode, AppMode::NormalMode));
+        }
+        self.show_dialog = true;
+        self.dialog_title = title;
+        self.dialog_content = content;
+        self.dialog_scroll = 0;
+        self.dialog_scroll_to_end = false;
+        self.pending_action = action;
+    }
+
+    // Scroll the content of the dialog by the given number of lines, the last line stays visible
+    fn scroll_dialog(&mut self, lines: i32) {
+        let max = self.dialog_content.lines().count().saturating_sub(1) as i32;
+        self.dialog_scroll = (self.dialog_scroll as i32)
+            .saturating_add(lines)
+            .max(0)
+            .min(max)
+            .min(u16::MAX as i32) as u16;
+    }
+
+    // Close the dialog, dropping the pending action if there is one
+    fn close_dialog(&mut self) {
+        self.show_dialog = false;
+        self.pending_action = None;
+        self.mode = self
+            .dialog_return_mode
+            .take()
+            .unwrap_or(AppMode::NormalMode);
+    }
+
+    // Open the command palette listing all the registered commands
+    fn open_palette(&mut self) {
+        self.show_palette = true;
+        self.palette_filter = String::new();
+        self.filter_palette();
+    }
+
+    // List in the palette the commands whose name or description contains the filter
+    fn filter_palette(&mut self) {
+        let filter = self.palette_filter.to_lowercase();
+        let mut commands: Vec<(String, String)> = self
+            .command_parser
+            .commands
+            .iter()
+            .map(|c| {
+                (
+                    c.get_name(),
+                    c.get_description().lines().next().unwrap_or("").to_string(),
+                )
+            })
+            .filter(|(name, description)| {
+                name.contains(&filter) || description.to_lowercase().contains(&filter)
+            })
+            .collect();
+        commands.sort();
+
+        self.palette.items = commands;
+        self.palette.state.select(if self.palette.items.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    // Handle a key while the palette is open
+    fn handle_palette(&mut self, key: Key) {
+        match key {
+            Key::Esc => self.show_palette = false,
+            // Run the selected command, or put it in the command view if it has arguments to type before running it
+            Key::Char('\n') => {
+                if let Some(i) = self.palette.state.selected() {
+                    let name = self.palette.items[i].0.clone();
+                    self.show_palette = false;
+                    let description = match self.command_parser.parse(name.clone()) {
+                        Ok((cmd, _)) => cmd.get_description(),
+                        Err(_) => String::new(),
+                    };
+                    if takes_arguments(&description) {
+                        self.command_buffer = format!("{} ", name);
+                        self.mode = AppMode::CommandMode;
+                    } else {
+                        self.execute_command(name);
+                    }
+                }
+            }
+            Key::Down => self.palette.next(),
+            Key::Up => self.palette.previous(),
+            Key::Char(c) => {
+                self.palette_filter.push(c);
+                self.filter_palette();
+            }
+            Key::Backspace => {
+                self.palette_filter.pop();
+                self.filter_palette();
+            }
+            _ => {}
+        }
+    }
+
+    // Handle the leader key and the sequence typed after it, returns true if the key has been consumed
+    fn handle_leader(&mut self, key: &Key) -> bool {
+        let leader = if let Some(l) = self.config.leader_key {
+            l
+        } else {
+            Config::default().leader_key.unwrap()
+        };
+
+        match (&self.mode, key) {
+            (AppMode::NormalMode, Key::Char(c)) if !self.show_dialog => {
+                if let Some(mut sequence) = self.leader_sequence.take() {
+                    sequence.push(*c);
+                    let bindings = self.config.leader_bindings.clone().unwrap_or_default();
+                    // Run the bound command, wait for more keys if the sequence is the start of a binding
+                    if let Some(command) = bindings.get(&sequence) {
+                        self.execute_command(command.clone());
+                    } else if bindings.keys().any(|b| b.starts_with(&sequence)) {
+                        self.leader_sequence = Some(sequence);
+                        self.leader_ticks = 0;
+                    } else {
+                        self.notify(
+                            StatusLevel::WARNING,
+                            format!("No leader binding for `{}`", sequence),
                         );
+                    }
+                    true
+                } else if *c == leader {
+                    self.leader_sequence = Some(String::new());
+                    self.leader_ticks = 0;
+                    true
+                } else {
+                    false
+                }
+            }
+            // `esc` aborts the sequence
+            (AppMode::NormalMode, Key::Esc) if self.leader_sequence.is_some() => {
+                self.leader_sequence = None;
+                true
+            }
+            _ => false,
+        }
+    }
 
-                    let mut items: Vec<ListItem> = Vec::new();
-                    app.items.items = Vec::new();
-                    for item in app.file_list.nodes.iter() {
-                        expand(
-                            item.clone(),
-                            &mut items,
-                            &mut app.items,
-                            app.config.borrow(),
+    // Parse and execute the given command line, errors are shown in the status
+    fn execute_command(&mut self, buffer: String) {
+        if let Ok(mut path) = self.current_file.write() {
+            *path = self.buffer.path.clone();
+        }
+        self.logger.log(
+            LogLevel::INFO,
+            format!("Executing the command `{}`", buffer),
+        );
+        // Parse the command with te command parser
+        let result = match self.command_parser.parse(buffer.clone()) {
+            Ok((cmd, tx)) => {
+                // Get the arguments
+                let args = CommandParser::arguments(&buffer);
+                // Execute the command and check for errors
+                match cmd.execute(tx, &args) {
+                    // If the syntax is invalid point to the help of the command
+                    Err(crate::commands::CommandError::InvalidSyntax) => {
+                        let text = format!("Invalid syntax! Type `help {}`", cmd.get_name());
+                        self.notify(StatusLevel::ERROR, text);
+                        Ok(())
+                    }
+                    result => result,
+                }
+            }
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            match e {
+                // If the command is not found, show it in the status
+                crate::commands::CommandError::NotFound => {
+                    self.notify(StatusLevel::ERROR, "Command not found!".to_string());
+                }
+                // If the command has an invalid syntaxt, show it in the status
+                crate::commands::CommandError::InvalidSyntax => {
+                    self.notify(StatusLevel::ERROR, "Invalid syntax!".to_string());
+                }
+                // If an execution error is throwed
+                crate::commands::CommandError::ExecutionError(e) => {
+                    // If a description is provided, show it in the status
+                    if let Some(e) = e {
+                        self.notify(
+                            StatusLevel::ERROR,
+                            format!("Error while executing the command: {}", &e),
+                        );
+                    // Else say that an unknown error has been catched
+                    } else {
+                        self.notify(
+                            StatusLevel::ERROR,
+                            "Error while executing the command: Unknown error".to_string(),
                         );
                     }
+                }
+            }
+        }
+    }
 
-                    // Create a List from all list items and highlight the currently selected one
-                    let items = List::new(items).block(files).highlight_style(
-                        Style::default()
-                            .bg(Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.explorer_selected_background {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().explorer_selected_background.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().explorer_selected_background.unwrap())
-                                },
-                            )
-                            .unwrap())
-                            .fg(Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.explorer_selected_foreground {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().explorer_selected_foreground.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().explorer_selected_foreground.unwrap())
-                                },
-                            )
-                            .unwrap())
-                            .add_modifier(Modifier::BOLD),
-                    );
+    // Check if the given pane has the focus, while in command mode the focus goes to the command view
+    fn is_focused(&self, pane: Pane) -> bool {
+        match (&self.mode, &self.focus, pane) {
+            (AppMode::CommandMode, _, _) => false,
+            (_, Pane::Explorer, Pane::Explorer) | (_, Pane::Editor, Pane::Editor) => true,
+            _ => false,
+        }
+    }
+
+    // Apply the parts of the configuration that need to be set up when it is loaded
+    fn apply_config(&mut self) {
+        self.reset_explorer_width();
+
+        // With monochrome or `NO_COLOR` set, only the default colors of the terminal are used
+        let no_color = env::var_os("NO_COLOR").map_or(false, |v| !v.is_empty());
+        let monochrome = no_color || self.config.monochrome == Some(true);
+        if monochrome {
+            self.config.theme = Some(self.config.theme.clone().unwrap_or_default().monochrome());
+        }
+        self.colors = ResolvedTheme::new(self.config.theme.clone());
+        // Without colors the selection is shown in reverse video
+        if monochrome {
+            self.colors.selected_modifier = Modifier::REVERSED;
+        }
+
+        self.load_keybindings();
+
+        // Colors that can't be parsed are drawn black, warn about them so typos are noticed
+        let invalid = self
+            .config
+            .theme
+            .clone()
+            .unwrap_or_default()
+            .invalid_colors();
+        if !invalid.is_empty() {
+            self.notify(
+                StatusLevel::WARNING,
+                format!("Invalid theme colors: {}", invalid.join(", ")),
+            );
+        }
+
+        // An unknown log format falls back to text, warn about it too
+        if let Some(format) = self.config.log_format.clone() {
+            if LogFormat::from_name(&format).is_none() {
+                self.notify(
+                    StatusLevel::WARNING,
+                    format!("Invalid log format {}, using text", format),
+                );
+            }
+        }
+    }
+
+    // Bind the keys of the normal mode, the configured keys replace the default ones of their actions
+    fn load_keybindings(&mut self) {
+        let custom = self.config.keybindings.clone().unwrap_or_default();
+        let mut problems = Vec::new();
 
-                    f.render_stateful_widget(items, chunks[0], &mut app.items.state);
+        let mut invalid = Vec::new();
+        for (action, key) in custom.iter() {
+            if Action::from_name(action).is_none() {
+                invalid.push(format!("{} = \"{}\"", action, key));
+            }
+        }
+
+        // An action whose configured key can't be parsed keeps its default key
+        let mut keys = Vec::new();
+        for (action, default) in default_keybindings() {
+            let mut key = parse_key(default).unwrap();
+            if let Some(k) = custom.get(action) {
+                match parse_key(k) {
+                    Some(k) => key = k,
+                    None => invalid.push(format!("{} = \"{}\"", action, k)),
                 }
+            }
+            keys.push((action, key));
+        }
+        if !invalid.is_empty() {
+            invalid.sort();
+            problems.push(format!("Invalid keybindings: {}", invalid.join(", ")));
+        }
 
-                let status_chunks = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(20), Constraint::Percentage(80)])
-                    .split(top_chunks[0]);
-
-                // Status bar block
-                let mode_bar = Block::default()
-                    .border_style(
-                        Style::default()
-                            .bg(Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.status_bar_background {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().status_bar_background.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().status_bar_background.unwrap())
-                                },
-                            )
-                            .unwrap())
-                            .fg(Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.status_bar_foreground {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().status_bar_foreground.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().status_bar_foreground.unwrap())
-                                },
-                            )
-                            .unwrap()),
-                    )
-                    .borders(Borders::empty())
-                    .style(
-                        Style::default().bg(Theme::get_color_for(
-                            if let Some(theme) = app.config.theme.clone() {
-                                if let Some(k) = theme.status_bar_background {
-                                    Some(k)
-                                } else {
-                                    Some(Theme::default().status_bar_background.unwrap())
-                                }
-                            } else {
-                                Some(Theme::default().status_bar_background.unwrap())
-                            },
-                        )
-                        .unwrap()),
-                    );
+        // A key bound to several actions runs the first one in the order of the defaults, so the result doesn't
+        // depend on the order of the configuration
+        let mut owners: HashMap<Key, &str> = HashMap::new();
+        self.keybindings = HashMap::new();
+        for (action, key) in keys {
+            if let Some(owner) = owners.get(&key) {
+                problems.push(format!(
+                    "{} is bound to both {} and {}, {} is unbound",
+                    key_name(key),
+                    owner,
+                    action,
+                    action
+                ));
+                continue;
+            }
+            owners.insert(key, action);
+            self.keybindings
+                .insert(key, Action::from_name(action).unwrap());
+        }
 
-                let status_bar = Block::default()
-                    .border_style(
-                        Style::default()
-                            .bg(Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.status_bar_background {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().status_bar_background.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().status_bar_background.unwrap())
-                                },
-                            )
-                            .unwrap())
-                            .fg(Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.status_bar_foreground {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().status_bar_foreground.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().status_bar_foreground.unwrap())
-                                },
-                            )
-                            .unwrap()),
-                    )
-                    .borders(Borders::empty())
-                    .style(
-                        Style::default().bg(Theme::get_color_for(
-                            if let Some(theme) = app.config.theme.clone() {
-                                if let Some(k) = theme.status_bar_background {
-                                    Some(k)
-                                } else {
-                                    Some(Theme::default().status_bar_background.unwrap())
-                                }
-                            } else {
-                                Some(Theme::default().status_bar_background.unwrap())
-                            },
-                        )
-                        .unwrap()),
+        if !problems.is_empty() {
+            self.notify(StatusLevel::WARNING, problems.join(". "));
+        }
+    }
+
+    // Set the explorer width to the configured one
+    fn reset_explorer_width(&mut self) {
+        let width = if let Some(w) = self.config.explorer_width {
+            w
+        } else {
+            Config::default().explorer_width.unwrap()
+        };
+        self.resize_explorer(width as i32);
+    }
+
+    // Set the explorer width (percentage of the frame), clamped so that the editor never disappears
+    fn resize_explorer(&mut self, width: i32) {
+        self.explorer_width = width
+            .max(MIN_EXPLORER_WIDTH as i32)
+            .min(MAX_EXPLORER_WIDTH as i32) as u16;
+    }
+
+    // Sort the explorer with the configured sorting, keeping the selected node selected
+    fn sort_explorer(&mut self) {
+        let selected = if let Some(ind) = self.items.state.selected() {
+            self.items.items.get(ind).map(|n| n.value.clone())
+        } else {
+            None
+        };
+
+        let sort = if let Some(sort) = &self.config.explorer_sort {
+            ExplorerSort::from_name(sort).unwrap_or(ExplorerSort::Name)
+        } else {
+            ExplorerSort::from_name(&Config::default().explorer_sort.unwrap()).unwrap()
+        };
+        let directories_first = if let Some(d) = self.config.directories_first {
+            d
+        } else {
+            Config::default().directories_first.unwrap()
+        };
+
+        self.file_list.sort(sort, directories_first);
+
+        if let Some(path) = selected {
+            self.items.state.select(
+                self.file_list
+                    .visible_paths()
+                    .iter()
+                    .position(|p| *p == path),
+            );
+        }
+    }
+
+    // Change an option of the configuration while the app is running
+    fn set_option(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "sort" => {
+                if ExplorerSort::from_name(value).is_none() {
+                    return Err(format!(
+                        "{} is not a valid sorting, use name, type or mtime",
+                        value
+                    ));
+                }
+                self.config.explorer_sort = Some(value.to_string());
+                self.sort_explorer();
+            }
+            "dirsfirst" => {
+                if let Some(d) = parse_switch(value) {
+                    self.config.directories_first = Some(d);
+                    self.sort_explorer();
+                } else {
+                    return Err(format!("{} is not a valid value, use on or off", value));
+                }
+            }
+            _ => return Err(format!("{} option doesn't exist", option)),
+        }
+
+        Ok(())
+    }
+
+    // Reload the explorer to show the files changed outside of the editor
+    fn refresh_explorer(&mut self) {
+        if self.working_path.is_none() {
+            return;
+        }
+        match self.load_explorer() {
+            Ok(_) => self.notify(StatusLevel::INFO, "Explorer refreshed".to_string()),
+            Err(e) => self.notify(
+                StatusLevel::ERROR,
+                format!("Error while loading the explorer: {}", e),
+            ),
+        }
+    }
+
+    // Open the selected file, asking for a confirmation if the changes to the current buffer would be lost
+    fn open_selected(&mut self) {
+        let node = match self.items.state.selected() {
+            Some(ind) => self.items.items[ind].clone(),
+            None => return,
+        };
+        if let NodeType::File = node.node_type {
+            if self.buffer.dirty {
+                self.open_dialog(
+                    "Unsaved changes".to_string(),
+                    format!(
+                        "The changes to the current file will be lost, open {} anyway?",
+                        self.display_path(Path::new(&node.value))
+                    ),
+                    Some(AppEvent::OpenFile(node.value)),
+                );
+            } else {
+                self.open_file(&node.value);
+            }
+        }
+    }
+
+    // Write the buffer to its file or to the given one, asking before replacing another existing file
+    fn save(&mut self, path: Option<String>, overwrite: bool) {
+        let target = path.as_ref().map(|p| self.resolve_path(p));
+        if let (Some(raw), Some(target)) = (&path, &target) {
+            let same_file = match (&self.buffer.path, fs::canonicalize(target)) {
+                (Some(current), Ok(target)) => fs::canonicalize(current).ok() == Some(target),
+                _ => false,
+            };
+            if target.exists() && !same_file && !overwrite {
+                self.open_dialog(
+                    "Overwrite".to_string(),
+                    format!("{} already exists, replace it?", self.display_path(target)),
+                    Some(AppEvent::ForceSave(raw.clone())),
+                );
+                return;
+            }
+        }
+
+        match self.buffer.write(target) {
+            Ok(written) => {
+                // Saving as another file can add it to the explorer
+                if path.is_some() {
+                    self.refresh_explorer();
+                }
+                let text = format!("Written {}", self.display_path(&written));
+                self.notify(StatusLevel::INFO, text);
+            }
+            Err(e) => self.notify(StatusLevel::ERROR, format!("Cannot write: {}", e)),
+        }
+    }
+
+    // Load the given file in the editor
+    pub fn open_file(&mut self, path: &str) {
+        let path = self.resolve_path(path);
+        match Buffer::open(path.clone()) {
+            Ok(buffer) => {
+                self.buffer = buffer;
+                self.focus = Pane::Editor;
+                self.logger
+                    .log(LogLevel::INFO, format!("Opened {}", path.to_string_lossy()));
+            }
+            Err(e) => self.notify(
+                StatusLevel::ERROR,
+                format!("Cannot open {}: {}", self.display_path(&path), e),
+            ),
+        }
+    }
+
+    // Expand or collapse the directory selected in the explorer, its children are read the first time
+    fn toggle_selected(&mut self) {
+        let mut errors = Vec::new();
+        let mut loaded = false;
+        if let Some(ind) = self.items.state.selected() {
+            if let Some(node) = self
+                .file_list
+                .from_path(&self.items.items.index_mut(ind).value)
+            {
+                if let Some(exp) = node.expanded {
+                    if node.children.is_none() {
+                        node.children = Some(read_children(
+                            Path::new(&node.value),
+                            node.layer + 1,
+                            &mut errors,
+                        ));
+                        loaded = true;
+                    }
+                    node.expanded = Some(!exp);
+                }
+            }
+        }
+
+        for error in errors {
+            self.logger.log(LogLevel::ERROR, error);
+        }
+        // Sort the new children like the rest of the tree
+        if loaded {
+            self.sort_explorer();
+        }
+    }
+
+    // Open the given directory as workspace, using its `.ledit.toml` over the global configuration
+    pub fn set_workspace(&mut self, path: String) -> Result<(), Box<dyn Error>> {
+        self.logger
+            .log(LogLevel::INFO, format!("Opening the workspace {}", path));
+        // The current workspace is kept if the new one can't be opened
+        if !Path::new(&path).is_dir() {
+            return Err(
+                format!("{} is not a directory", self.display_path(Path::new(&path))).into(),
+            );
+        }
+        self.working_path = Some(path);
+        self.load_project_config();
+        self.load_explorer()
+    }
+
+    fn load_project_config(&mut self) {
+        self.config = self.global_config.clone();
+        self.apply_config();
+
+        let path = match &self.working_path {
+            Some(workspace) => Path::new(workspace).join(".ledit.toml"),
+            None => return,
+        };
+
+        if let Ok(content) = fs::read_to_string(&path) {
+            match self.global_config.merge_project(&content) {
+                Ok((config, ignored)) => {
+                    self.config = config;
+                    self.apply_config();
+                    self.logger.log(
+                        LogLevel::INFO,
+                        format!("Loaded the project configuration from {}", path.display()),
                     );
-                // Current mode as string
-                let current_mode = match app.mode {
-                    AppMode::InsertMode => "Insert Mode",
-                    AppMode::CommandMode => "Command Mode",
-                    AppMode::NormalMode => "Normal Mode",
-                };
-
-                // Status paragraph
-                let mode_paragraph =
-                    Paragraph::new(Spans::from(format!("Current Mode: {}", current_mode)))
-                        .wrap(Wrap { trim: true })
-                        .block(mode_bar)
-                        .style(Style::default().add_modifier(Modifier::BOLD));
-
-                let status_paragraph = Paragraph::new(Spans::from(Span::styled(
-                    app.status.text.clone(),
-                    Style::default()
-                        .fg(match app.status.level {
-                            StatusLevel::ERROR => Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.status_error {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().status_error.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().status_error.unwrap())
-                                },
-                            )
-                            .unwrap(),
-                            StatusLevel::INFO => Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.status_info {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().status_info.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().status_info.unwrap())
-                                },
-                            )
-                            .unwrap(),
-                            StatusLevel::WARNING => Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.status_warning {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().status_warning.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().status_warning.unwrap())
-                                },
-                            )
-                            .unwrap(),
-                        })
-                        .add_modifier(Modifier::BOLD),
-                )))
-                .wrap(Wrap { trim: true })
-                .block(status_bar)
-                .style(Style::default().add_modifier(Modifier::BOLD));
-
-                f.render_widget(mode_paragraph, status_chunks[0]);
-                f.render_widget(status_paragraph, status_chunks[1]);
-
-                // If the command view is open, render it
-                if let AppMode::CommandMode = app.mode {
-                    let command_view = Block::default()
-                        .title("Commands")
-                        .border_style(
-                            Style::default().fg(Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.active_view_border {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().active_view_border.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().active_view_border.unwrap())
-                                },
-                            )
-                            .unwrap()),
-                        )
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Plain)
-                        .style(
-                            Style::default().bg(Theme::get_color_for(
-                                if let Some(theme) = app.config.theme.clone() {
-                                    if let Some(k) = theme.commands_view_background {
-                                        Some(k)
-                                    } else {
-                                        Some(Theme::default().commands_view_background.unwrap())
-                                    }
-                                } else {
-                                    Some(Theme::default().commands_view_background.unwrap())
-                                },
-                            )
-                            .unwrap()),
+                    if !ignored.is_empty() {
+                        self.notify(
+                            StatusLevel::WARNING,
+                            format!(
+                                "{} can only be set in the global configuration, ignored in {}",
+                                ignored.join(", "),
+                                self.display_path(&path)
+                            ),
                         );
+                    }
+                }
+                Err(e) => self.notify(
+                    StatusLevel::WARNING,
+                    format!("Cannot load {}: {}", self.display_path(&path), e),
+                ),
+            }
+        }
+    }
 
-                    let command_paragraph =
-                        Paragraph::new(format!("> {}", app.command_buffer)).block(command_view);
+    pub fn load_explorer(&mut self) -> Result<(), Box<dyn Error>> {
+        // Remember which directories were expanded so that a reload keeps the tree shape
+        let expanded = self.file_list.expanded_paths();
 
-                    f.render_widget(command_paragraph, bottom_chunks[1]);
-                    f.set_cursor(
-                        bottom_chunks[1].x + app.command_buffer.len() as u16 + 3,
-                        bottom_chunks[1].y + 1,
-                    );
+        if let Some(workspace_path) = &self.working_path {
+            self.branch = git_branch(Path::new(workspace_path));
+            let mut expl = Vec::new();
+            let path = Path::new(workspace_path);
+            if !path.is_dir() {
+                return Err(format!("{} is not a directory", workspace_path).into());
+            }
+            let mut errors = Vec::new();
+            for entry in path.read_dir()? {
+                match entry {
+                    Ok(en) => expl.push(read_node(en, 0)),
+                    Err(e) => {
+                        errors.push(format!("Cannot read an entry of {}: {}", workspace_path, e))
+                    }
                 }
+            }
+            self.file_list.nodes = expl;
 
-                // Editor block
-                let editor = Block::default()
-                    .border_style(Style::default().fg(if let AppMode::InsertMode = app.mode {
-                        Theme::get_color_for(if let Some(theme) = app.config.theme.clone() {
-                            if let Some(k) = theme.active_view_border {
-                                Some(k)
-                            } else {
-                                Some(Theme::default().active_view_border.unwrap())
-                            }
-                        } else {
-                            Some(Theme::default().active_view_border.unwrap())
-                        })
-                        .unwrap()
-                    } else {
-                        Theme::get_color_for(if let Some(theme) = app.config.theme.clone() {
-                            if let Some(k) = theme.view_border {
-                                Some(k)
-                            } else {
-                                Some(Theme::default().view_border.unwrap())
-                            }
-                        } else {
-                            Some(Theme::default().view_border.unwrap())
-                        })
-                        .unwrap()
-                    }))
-                    .borders(Borders::ALL)
-                    .title("Editor")
-                    .border_type(BorderType::Plain)
-                    .style(
-                        Style::default().bg(Theme::get_color_for(
-                            if let Some(theme) = app.config.theme.clone() {
-                                if let Some(k) = theme.editor_background {
-                                    Some(k)
-                                } else {
-                                    Some(Theme::default().editor_background.unwrap())
-                                }
-                            } else {
-                                Some(Theme::default().editor_background.unwrap())
-                            },
-                        )
-                        .unwrap()),
-                    );
+            // Parents come before their children, so they are loaded before the children are looked up
+            for path in expanded.iter() {
+                if let Some(node) = self.file_list.from_path(path) {
+                    if node.expanded.is_some() {
+                        if node.children.is_none() {
+                            node.children =
+                                Some(read_children(Path::new(path), node.layer + 1, &mut errors));
+                        }
+                        node.expanded = Some(true);
+                    }
+                }
+            }
 
-                f.render_widget(editor, chunks[1]);
-            })
-            .unwrap();
+            for error in errors {
+                self.logger.log(LogLevel::ERROR, error);
+            }
+        } else {
+            self.file_list.nodes = vec![Node::new(
+                "Empty workspace".to_string(),
+                "".to_string(),
+                None,
+                None,
+                0,
+                NodeType::Info,
+            )];
+            self.branch = None;
+            self.logger.log(
+                LogLevel::WARN,
+                "No workspace directory is provided, using empty workspace".to_string(),
+            );
+        }
+
+        self.sort_explorer();
+
+        Ok(())
+    }
+}
+
+// Removes the panic hook installed by `render` when dropped
+struct PanicHookGuard;
+
+impl Drop for PanicHookGuard {
+    fn drop(&mut self) {
+        // The hook can't be changed while panicking, it is left to print the message
+        if !thread::panicking() {
+            let _ = panic::take_hook();
+        }
+    }
+}
+
+// Render method, this is the main loop that renders all the TUI
+pub fn render(app: &mut App) -> Result<(), Box<dyn Error>> {
+    // The raw mode is shared with the panic hook, so a crash restores the terminal before the panic message
+    // is printed, the guard puts back the default hook when the render loop exits
+    let raw_mode = Arc::new(io::stdout().into_raw_mode()?);
+    let hook_raw_mode = raw_mode.clone();
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = hook_raw_mode.suspend_raw_mode();
+        let mut stdout = io::stdout();
+        let _ = write!(
+            stdout,
+            "{}{}{}",
+            DISABLE_BRACKETED_PASTE,
+            ToMainScreen,
+            cursor::Show
+        );
+        let _ = stdout.flush();
+        default_hook(info);
+    }));
+    let _panic_hook = PanicHookGuard;
+
+    let stdout = MouseTerminal::from(io::stdout());
+    let mut stdout = AlternateScreen::from(stdout);
+    write!(stdout, "{}", ENABLE_BRACKETED_PASTE)?;
+    let backend = TermionBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let result = run(&mut terminal, app);
+    if let Err(e) = &result {
+        app.logger.log(
+            LogLevel::ERROR,
+            format!("The app stopped on an error: {}", e),
+        );
+    }
+    write!(terminal.backend_mut(), "{}", DISABLE_BRACKETED_PASTE)?;
+    result
+}
+
+// Run the app on the given terminal until it is closed, the backend can be replaced for testing
+fn run<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<(), Box<dyn Error>> {
+    app.logger
+        .log(LogLevel::INFO, "Loading the explorer".to_string());
+    if let Err(e) = app.load_explorer() {
+        app.logger
+            .log(LogLevel::ERROR, format!("Cannot load the explorer: {}", e));
+        app.notify(StatusLevel::ERROR, "Cannot load explorer!".to_string());
+    } else {
+        app.logger
+            .log(LogLevel::INFO, "Explorer loaded".to_string());
+    }
+
+    loop {
+        // If the app should close, close it
+        if app.should_close {
+            break;
+        }
+        terminal.draw(|f| draw(f, app)).unwrap();
 
         // Check for events
-        match app.events.next().unwrap() {
+        let event = app.events.next().unwrap();
+        let count = app.update_count(&event);
+        match event {
+            // While the palette is open it takes all the keys
+            Event::Input(input) if app.show_palette => app.handle_palette(input),
+            // Keys typed after the leader are handled separately
+            Event::Input(input) if app.handle_leader(&input) => {}
             Event::Input(input) => match app.mode {
-                AppMode::NormalMode => match input {
-                    // If `enter` is pressed and the dialog is open, close it
-                    Key::Char('\n') => {
+                AppMode::NormalMode => match app.keybindings.get(&input).copied() {
+                    // Close the dialog unless it is waiting for a choice, else open the file selected in the explorer
+                    Some(Action::Open) => {
                         if app.show_dialog {
-                            app.show_dialog = false;
+                            if app.pending_action.is_none() {
+                                app.close_dialog();
+                            }
+                        } else if app.file_view && app.is_focused(Pane::Explorer) {
+                            app.open_selected();
                         }
                     }
-                    // If 'q' is pressed, quit the app
-                    Key::Char('q') => {
-                        if !app.show_dialog {
+                    // While a confirmation dialog is open, run the pending action
+                    Some(Action::Confirm) => {
+                        if app.show_dialog {
+                            if let Some(action) = app.pending_action.take() {
+                                app.close_dialog();
+                                if block_on(app.transmitter.send(action)).is_err() {
+                                    app.notify(
+                                        StatusLevel::ERROR,
+                                        "Error while sending the confirmed action to the application"
+                                            .to_string(),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    // While a confirmation dialog is open, abort the pending action
+                    Some(Action::Cancel) => {
+                        if app.show_dialog {
+                            if app.pending_action.is_some() {
+                                app.close_dialog();
+                            }
+                        }
+                    }
+                    // Close the dialog if it is open, else quit the app
+                    Some(Action::Quit) => {
+                        if app.show_dialog {
+                            app.close_dialog();
+                        } else {
                             app.close()
                         }
                     }
-                    // If 'f' is pressed open/close the explorer
-                    Key::Char('f') => {
+                    // Open/close the explorer, the editor takes the focus if the explorer is closed
+                    Some(Action::ToggleExplorer) => {
+                        if !app.show_dialog {
+                            app.file_view = !app.file_view;
+                            if !app.file_view {
+                                app.focus = Pane::Editor;
+                            }
+                        }
+                    }
+                    // Move the focus to the other pane
+                    Some(Action::SwitchPane) => {
+                        if !app.show_dialog && app.file_view {
+                            app.focus = if let Pane::Explorer = app.focus {
+                                Pane::Editor
+                            } else {
+                                Pane::Explorer
+                            };
+                        }
+                    }
+                    // Shrink or grow the explorer
+                    Some(Action::ShrinkExplorer) => {
+                        if !app.show_dialog && app.file_view {
+                            app.resize_explorer(
+                                (app.explorer_width as i32).saturating_sub(count as i32 * 5),
+                            );
+                        }
+                    }
+                    Some(Action::GrowExplorer) => {
+                        if !app.show_dialog && app.file_view {
+                            app.resize_explorer(
+                                (app.explorer_width as i32).saturating_add(count as i32 * 5),
+                            );
+                        }
+                    }
+                    // Reload the explorer
+                    Some(Action::Refresh) => {
                         if !app.show_dialog {
-                            app.file_view = !app.file_view
+                            app.refresh_explorer();
                         }
                     }
-                    // If 'c' is pressed go in command mode
-                    Key::Char('c') => {
+                    // Open the command palette
+                    Some(Action::Palette) => {
                         if !app.show_dialog {
-                            app.mode = AppMode::CommandMode
+                            app.open_palette();
                         }
                     }
-                    // If 'i' is pressed go in insert mode
-                    Key::Char('i') => {
+                    // Go in command mode
+                    Some(Action::CommandMode) => {
                         if !app.show_dialog {
-                            app.mode = AppMode::InsertMode
+                            app.mode = AppMode::CommandMode
                         }
                     }
-                    // If the left arrow is pressed unselect the entry from the explorer
-                    Key::Esc => {
+                    // Go in insert mode, editing happens in the editor
+                    Some(Action::InsertMode) => {
                         if !app.show_dialog {
-                            if app.file_view {
-                                app.items.unselect();
+                            app.mode = AppMode::InsertMode;
+                            app.focus = Pane::Editor;
+                        }
+                    }
+                    // Close the dialog, or unselect the entry from the explorer
+                    Some(Action::Close) => {
+                        if app.show_dialog {
+                            app.close_dialog();
+                        } else if app.file_view && app.is_focused(Pane::Explorer) {
+                            app.items.unselect();
+                        }
+                    }
+                    // Scroll the dialog down, or select the next entry in the explorer, or the next line in the editor
+                    Some(Action::Down) => {
+                        if app.show_dialog {
+                            app.scroll_dialog(count as i32);
+                        } else if app.file_view && app.is_focused(Pane::Explorer) {
+                            for _ in 0..count {
+                                app.items.next();
+                            }
+                        } else if app.is_focused(Pane::Editor) {
+                            for _ in 0..count {
+                                app.buffer.move_down();
+                            }
+                        }
+                    }
+                    // Scroll the dialog up, or select the previous entry in the explorer, or the previous line in the
+                    // editor
+                    Some(Action::Up) => {
+                        if app.show_dialog {
+                            app.scroll_dialog(-(count as i32));
+                        } else if app.file_view && app.is_focused(Pane::Explorer) {
+                            for _ in 0..count {
+                                app.items.previous();
+                            }
+                        } else if app.is_focused(Pane::Editor) {
+                            for _ in 0..count {
+                                app.buffer.move_up();
                             }
                         }
                     }
-                    // If the down arrow is pressed select the next entry in the explorer
-                    Key::Down => {
-                        if !app.show_dialog {
-                            if app.file_view {
-                                app.items.next();
+                    // Move the cursor of the editor
+                    Some(Action::Left) => {
+                        if !app.show_dialog && app.is_focused(Pane::Editor) {
+                            for _ in 0..count {
+                                app.buffer.move_left();
                             }
                         }
                     }
-                    // If the up arrow is pressed select the previous entry in the explorer
-                    Key::Up => {
-                        if !app.show_dialog {
-                            if app.file_view {
-                                app.items.previous();
+                    Some(Action::Right) => {
+                        if !app.show_dialog && app.is_focused(Pane::Editor) {
+                            for _ in 0..count {
+                                app.buffer.move_right();
                             }
                         }
                     }
-                    // If the right arrow is pressed expand the selected node
-                    Key::Char(' ') => {
-                        if !app.show_dialog {
-                            if let Some(ind) = app.items.state.selected() {
-                                if let Some(node) = app
-                                    .file_list
-                                    .from_uuid(&app.items.items.index_mut(ind).uuid)
-                                {
-                                    if let Some(exp) = node.expanded {
-                                        node.expanded = Some(!exp);
-                                    }
-                                }
-                            }
+                    // Expand or collapse the selected node
+                    Some(Action::ExpandNode) => {
+                        if !app.show_dialog && app.is_focused(Pane::Explorer) {
+                            app.toggle_selected();
                         }
                     }
-                    _ => {}
+                    None => {}
                 },
                 // When the app is in insert mode
                 AppMode::InsertMode => match input {
                     // If `esc` is pressed go in normal mode
                     Key::Esc => app.mode = AppMode::NormalMode,
+                    // Edit the buffer, `enter` splits the line at the cursor
+                    Key::Char(c) => app.buffer.insert(c),
+                    Key::Backspace => app.buffer.backspace(),
+                    Key::Left => app.buffer.move_left(),
+                    Key::Right => app.buffer.move_right(),
+                    Key::Up => app.buffer.move_up(),
+                    Key::Down => app.buffer.move_down(),
                     _ => {}
                 },
                 // When the app is in command mode
@@ -988,70 +1934,9 @@ pub fn render(app: &mut App) -> Result<(), Box<dyn Error>> {
                     Key::Esc => app.mode = AppMode::NormalMode,
                     // If `enter` is pressed and the command buffer is not empty
                     Key::Char('\n') => {
-                        if app.command_buffer != "" {
-                            // Parse the command with te command parser
-                            match app.command_parser.parse(app.command_buffer.clone()) {
-                                Ok((cmd, tx)) => {
-                                    // Get the arguments
-                                    let mut args: Vec<String> = app
-                                        .command_buffer
-                                        .clone()
-                                        .split(' ')
-                                        .map(|a| String::from(a))
-                                        .collect();
-                                    args.remove(0);
-                                    if let Err(crate::commands::CommandError::InvalidSyntax) =
-                                        cmd.execute(tx, &args)
-                                    // Execute the command and check for errors
-                                    {
-                                        // If there is an error show it in the status
-                                        app.status = Status {
-                                            text: format!(
-                                                "Invalid syntax! Type `help {}`",
-                                                cmd.get_name()
-                                            )
-                                            .to_string(),
-                                            level: crate::util::StatusLevel::ERROR,
-                                        }
-                                    }
-                                }
-                                Err(e) => match e {
-                                    // If the command is not found, show it in the status
-                                    crate::commands::CommandError::NotFound => {
-                                        app.status = Status {
-                                            text: "Command not found!".to_string(),
-                                            level: crate::util::StatusLevel::ERROR,
-                                        }
-                                    }
-                                    // If the command has an invalid syntaxt, show it in the status
-                                    crate::commands::CommandError::InvalidSyntax => {
-                                        app.status = Status {
-                                            text: "Invalid syntax!".to_string(),
-                                            level: crate::util::StatusLevel::ERROR,
-                                        }
-                                    }
-                                    // If an execution error is throwed
-                                    crate::commands::CommandError::ExecutionError(e) => {
-                                        // If a description is provided, show it in the status
-                                        if let Some(e) = e {
-                                            app.status = Status {
-                                                text: format!(
-                                                    "Error while executing the command: {}",
-                                                    &e
-                                                ),
-                                                level: crate::util::StatusLevel::ERROR,
-                                            }
-                                        // Else say that an unknown error has been catched
-                                        } else {
-                                            app.status = Status {
-                                                text: "Error while executing the command: Unknown error"
-                                                    .to_string(),
-                                                level: crate::util::StatusLevel::ERROR,
-                                            }
-                                        }
-                                    }
-                                },
-                            }
+                        if !app.command_buffer.is_empty() {
+                            let buffer = app.command_buffer.clone();
+                            app.execute_command(buffer);
                             // Free the command buffer
                             app.command_buffer = String::new();
                         }
@@ -1065,49 +1950,1171 @@ pub fn render(app: &mut App) -> Result<(), Box<dyn Error>> {
                     _ => {}
                 },
             },
-            Event::Tick => (),
-        }
-
-        // This checks the receiver that is bound to a sender used by commands
-        match app.receiver.try_recv() {
-            // Close the application if requested
-            Ok(AppEvent::Close) => app.close(),
-            // Show a dialog with the given information
-            Ok(AppEvent::ShowDialog((title, content))) => {
-                app.show_dialog = true;
-                app.dialog_content = content;
-                app.mode = AppMode::NormalMode;
-                app.dialog_title = title;
-            }
-            // Set the status with the given information
-            Ok(AppEvent::SetStatus(s)) => {
-                app.status = s;
+            // Pasted text is inserted at once, in the command view the new lines become spaces
+            Event::Paste(text) => {
+                if app.show_palette {
+                    app.palette_filter.push_str(&text.replace('\n', " "));
+                    app.filter_palette();
+                } else if let AppMode::CommandMode = app.mode {
+                    app.command_buffer.push_str(&text.replace('\n', " "));
+                } else if let AppMode::InsertMode = app.mode {
+                    app.buffer.insert_str(&text);
+                }
             }
-            // Set the workspace to the given path
-            Ok(AppEvent::SetWorkspace(w)) => {
-                app.working_path = Some(w);
-                if let Err(_) = app.load_explorer() {
-                    app.status = Status {
-                        text: "Error while loading the explorer".to_string(),
-                        level: StatusLevel::ERROR,
-                    };
+            Event::Tick => {
+                // Advance the spinner of the running operation
+                if app.progress.is_some() {
+                    app.spinner_frame = (app.spinner_frame + 1) % SPINNER_FRAMES.len();
+                }
+                // Clear the leader sequence if no key has been typed for a while
+                if app.leader_sequence.is_some() {
+                    app.leader_ticks += 1;
+                    if app.leader_ticks >= LEADER_TIMEOUT_TICKS {
+                        app.leader_sequence = None;
+                    }
                 }
+                app.poll_terminal();
+                app.check_workspace();
             }
-            // If there is an error while receiving, show it in the status
-            Err(e) => {
-                if e == TryRecvError::Closed {
-                    app.status = Status {
-                        text: format!("Error receiving application events: {:?}", &e),
-                        level: crate::util::StatusLevel::ERROR,
+        }
+
+        // This checks the receiver that is bound to a sender used by commands, handling the pending events up to a limit
+        for _ in 0..MAX_EVENTS_PER_FRAME {
+            match app.receiver.try_recv() {
+                // Close the application if requested
+                Ok(AppEvent::Close) => app.close(),
+                // Show a dialog with the given information
+                Ok(AppEvent::ShowDialog((title, content))) => app.open_dialog(title, content, None),
+                // Show a dialog that runs the given action only if the user confirms it
+                Ok(AppEvent::ShowConfirm((title, content, action))) => {
+                    app.open_dialog(title, content, Some(*action))
+                }
+                // Set the status with the given information
+                Ok(AppEvent::SetStatus(s)) => {
+                    app.notify(s.level, s.text);
+                }
+                // Show or clear the progress of a long operation
+                Ok(AppEvent::SetProgress(progress)) => {
+                    app.progress = progress;
+                    app.spinner_frame = 0;
+                    // The next events can block, draw now so that the progress is visible meanwhile
+                    if app.progress.is_some() {
+                        terminal.draw(|f| draw(f, app))?;
+                    }
+                }
+                // Change the given option
+                Ok(AppEvent::SetOption((option, value))) => match app.set_option(&option, &value) {
+                    Ok(_) => app.notify(StatusLevel::INFO, format!("{} set to {}", option, value)),
+                    Err(e) => app.notify(StatusLevel::ERROR, e),
+                },
+                // Set the workspace to the given path
+                Ok(AppEvent::SetWorkspace(w)) => {
+                    let path = app.resolve_path(&w).to_string_lossy().to_string();
+                    if let Err(e) = app.set_workspace(path) {
+                        app.notify(
+                            StatusLevel::ERROR,
+                            format!("Cannot open the workspace: {}", e),
+                        );
+                    }
+                }
+                // Open the command palette
+                Ok(AppEvent::ShowPalette) => app.open_palette(),
+                // Create a new file from a template, show it in the explorer and open it
+                Ok(AppEvent::NewFromTemplate((name, path))) => {
+                    match app.create_from_template(&name, &path) {
+                        Ok(target) => {
+                            if app.load_explorer().is_err() {
+                                app.notify(
+                                    StatusLevel::ERROR,
+                                    "Error while loading the explorer".to_string(),
+                                );
+                            }
+                            app.open_file(&target.to_string_lossy());
+                            app.notify(
+                                StatusLevel::INFO,
+                                format!("Created {} from {}", app.display_path(&target), name),
+                            );
+                        }
+                        Err(e) => app.notify(StatusLevel::ERROR, e),
+                    }
+                }
+                // Create a file or a directory and show it in the explorer
+                Ok(AppEvent::CreatePath(path)) => match app.create_path(&path) {
+                    Ok(target) => {
+                        if app.load_explorer().is_err() {
+                            app.notify(
+                                StatusLevel::ERROR,
+                                "Error while loading the explorer".to_string(),
+                            );
+                        }
+                        app.notify(
+                            StatusLevel::INFO,
+                            format!("Created {}", app.display_path(&target)),
+                        );
+                    }
+                    Err(e) => app.notify(StatusLevel::ERROR, e),
+                },
+                // Delete a file or a directory, this comes from a confirmation dialog
+                Ok(AppEvent::DeletePath(path)) => match app.delete_path(&path) {
+                    Ok(target) => {
+                        if app.load_explorer().is_err() {
+                            app.notify(
+                                StatusLevel::ERROR,
+                                "Error while loading the explorer".to_string(),
+                            );
+                        }
+                        app.notify(
+                            StatusLevel::INFO,
+                            format!("Deleted {}", app.display_path(&target)),
+                        );
+                    }
+                    Err(e) => app.notify(StatusLevel::ERROR, e),
+                },
+                Ok(AppEvent::RenamePath((old, new))) => match app.rename_path(&old, &new) {
+                    Ok((from, to)) => {
+                        if app.load_explorer().is_err() {
+                            app.notify(
+                                StatusLevel::ERROR,
+                                "Error while loading the explorer".to_string(),
+                            );
+                        }
+                        app.notify(
+                            StatusLevel::INFO,
+                            format!(
+                                "Renamed {} to {}",
+                                app.display_path(&from),
+                                app.display_path(&to)
+                            ),
+                        );
+                    }
+                    Err(e) => app.notify(StatusLevel::ERROR, e),
+                },
+                // Load a file in the editor
+                Ok(AppEvent::OpenFile(path)) => app.open_file(&path),
+                // Write the editor buffer to its file, or to the given one
+                Ok(AppEvent::Save(path)) => app.save(path, false),
+                // Save to a file that already exists, the user confirmed it
+                Ok(AppEvent::ForceSave(path)) => app.save(Some(path), true),
+                // Show the current workspace and the open file in the status
+                Ok(AppEvent::ShowWorkspace) => {
+                    let workspace = match &app.working_path {
+                        Some(path) => path.clone(),
+                        None => "No workspace is open".to_string(),
                     };
-                    app.logger.log(
-                        LogLevel::ERROR,
-                        format!("Error receiving application events: {:?}", &e),
-                    )
+                    let text = match &app.buffer.path {
+                        Some(file) => format!("{}, editing {}", workspace, file.display()),
+                        None => workspace,
+                    };
+                    app.notify(StatusLevel::INFO, text);
+                }
+                Ok(AppEvent::RefreshExplorer) => app.refresh_explorer(),
+                // Show the last lines of the logs in a dialog
+                Ok(AppEvent::ShowLogs) => {
+                    let recent = app.logger.recent();
+                    let lines: Vec<String> = recent
+                        .iter()
+                        .skip(recent.len().saturating_sub(LOGS_DIALOG_LINES))
+                        .map(|entry| entry.to_string())
+                        .collect();
+                    app.open_dialog("Logs".to_string(), lines.join("\n"), None);
+                    // The newest lines are the interesting ones
+                    app.dialog_scroll_to_end = true;
                 }
+                // Run a command in the terminal pane
+                Ok(AppEvent::RunInTerminal(command)) => app.run_in_terminal(command),
+                // Close the terminal pane, stopping the command if it is still running
+                Ok(AppEvent::CloseTerminal) => app.close_terminal(),
+                // If the channel has been closed, report it once and replace it with a new one
+                Err(TryRecvError::Closed) => {
+                    app.reconnect_events();
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
             }
         }
     }
 
     Ok(())
 }
+
+// Draw a frame of the app
+fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    // Size for the current frame
+    let size = f.size();
+
+    // In compact mode the main block has no borders and no title, leaving more room to the panes
+    let compact = if let Some(c) = app.config.compact {
+        c
+    } else {
+        Config::default().compact.unwrap()
+    };
+
+    // Borders of the app
+    let (app_borders, app_border_type) = app.colors.app_borders;
+
+    // Main block
+    let block = Block::default()
+        .title(if compact {
+            String::new()
+        } else {
+            format!("LEdit v{}", env!("CARGO_PKG_VERSION"))
+        })
+        .border_style(Style::default().fg(app.colors.app_foreground))
+        .style(Style::default().bg(app.colors.app_background))
+        .border_type(app_border_type)
+        .borders(if compact { Borders::NONE } else { app_borders });
+    f.render_widget(block, size);
+
+    let top_chunks: Vec<Rect>;
+    let chunks: Vec<Rect>;
+    let bottom_chunks: Vec<Rect>;
+
+    top_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(4), Constraint::Percentage(96)])
+        .margin(if compact { 0 } else { 1 })
+        .split(size);
+
+    // If the command view is open set its with to the 20% of the frame and the rest to the 80%
+    if let AppMode::CommandMode = app.mode {
+        bottom_chunks = Layout::default()
+            .margin(0)
+            .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
+            .direction(Direction::Vertical)
+            .split(top_chunks[1]);
+    } else {
+        bottom_chunks = Layout::default()
+            .margin(0)
+            .constraints([Constraint::Percentage(100), Constraint::Percentage(0)])
+            .direction(Direction::Vertical)
+            .split(top_chunks[1]);
+    }
+
+    // If the explorer is open set its width to the configured one and give the rest to the editor, else the editor should have a width of 100%
+    if app.file_view {
+        chunks = Layout::default()
+            .margin(1)
+            .constraints(
+                [
+                    Constraint::Percentage(app.explorer_width),
+                    Constraint::Percentage(100 - app.explorer_width),
+                ]
+                .as_ref(),
+            )
+            .direction(Direction::Horizontal)
+            .split(bottom_chunks[0]);
+    } else {
+        chunks = Layout::default()
+            .margin(1)
+            .constraints([Constraint::Percentage(0), Constraint::Percentage(100)].as_ref())
+            .direction(Direction::Horizontal)
+            .split(bottom_chunks[0]);
+    }
+
+    // If the explorer is open, render it
+    if app.file_view {
+        // Borders of the explorer
+        let (explorer_borders, explorer_border_type) = app.colors.explorer_borders;
+
+        let files = Block::default()
+            .border_style(Style::default().fg(if app.is_focused(Pane::Explorer) {
+                app.colors.active_view_border
+            } else {
+                app.colors.view_border
+            }))
+            .borders(explorer_borders)
+            .title("Explorer")
+            .border_type(explorer_border_type)
+            .style(Style::default().bg(app.colors.explorer_background));
+
+        let mut items: Vec<ListItem> = Vec::new();
+        app.items.items = Vec::new();
+        for item in app.file_list.nodes.iter() {
+            expand(
+                item.clone(),
+                &mut items,
+                &mut app.items,
+                app.config.borrow(),
+                &app.colors,
+            );
+        }
+
+        // Create a List from all list items and highlight the currently selected one
+        let items = List::new(items).block(files).highlight_style(
+            Style::default()
+                .bg(app.colors.explorer_selected_background)
+                .fg(app.colors.explorer_selected_foreground)
+                .add_modifier(Modifier::BOLD | app.colors.selected_modifier),
+        );
+
+        f.render_stateful_widget(items, chunks[0], &mut app.items.state);
+    }
+
+    // Status bar block
+    let status_bar = Block::default()
+        .border_style(
+            Style::default()
+                .bg(app.colors.status_bar_background)
+                .fg(app.colors.status_bar_foreground),
+        )
+        .borders(Borders::empty())
+        .style(Style::default().bg(app.colors.status_bar_background));
+    // Current mode as string, with its colors
+    let (current_mode, mode_background, mode_foreground) = match app.mode {
+        AppMode::InsertMode => (
+            if let Some(label) = app.config.insert_mode_label.clone() {
+                label
+            } else {
+                Config::default().insert_mode_label.unwrap()
+            },
+            app.colors.insert_mode_background,
+            app.colors.insert_mode_foreground,
+        ),
+        AppMode::CommandMode => (
+            if let Some(label) = app.config.command_mode_label.clone() {
+                label
+            } else {
+                Config::default().command_mode_label.unwrap()
+            },
+            app.colors.command_mode_background,
+            app.colors.command_mode_foreground,
+        ),
+        AppMode::NormalMode => (
+            if let Some(label) = app.config.normal_mode_label.clone() {
+                label
+            } else {
+                Config::default().normal_mode_label.unwrap()
+            },
+            app.colors.normal_mode_background,
+            app.colors.normal_mode_foreground,
+        ),
+    };
+
+    // Values of the placeholders that can be used in the status format
+    let mut placeholders: HashMap<&str, Span> = HashMap::new();
+    placeholders.insert(
+        "mode",
+        Span::styled(
+            current_mode,
+            Style::default().bg(mode_background).fg(mode_foreground),
+        ),
+    );
+    placeholders.insert(
+        "status",
+        Span::styled(
+            app.status.text.clone(),
+            Style::default().fg(match app.status.level {
+                StatusLevel::ERROR => app.colors.status_error,
+                StatusLevel::INFO => app.colors.status_info,
+                StatusLevel::WARNING => app.colors.status_warning,
+            }),
+        ),
+    );
+    placeholders.insert(
+        "workspace",
+        Span::raw(if let Some(workspace) = &app.working_path {
+            app.display_path(Path::new(workspace))
+        } else {
+            String::new()
+        }),
+    );
+    placeholders.insert("branch", Span::raw(app.branch.clone().unwrap_or_default()));
+    placeholders.insert(
+        "file",
+        Span::raw(if let Some(path) = &app.buffer.path {
+            app.display_path(path)
+        } else {
+            String::new()
+        }),
+    );
+    placeholders.insert("line", Span::raw((app.buffer.cursor.0 + 1).to_string()));
+    placeholders.insert("col", Span::raw((app.buffer.cursor.1 + 1).to_string()));
+    placeholders.insert(
+        "dirty",
+        Span::raw(if app.buffer.dirty { "[+]" } else { "" }),
+    );
+    placeholders.insert(
+        "version",
+        Span::raw(format!("LEdit v{}", env!("CARGO_PKG_VERSION"))),
+    );
+    placeholders.insert(
+        "progress",
+        Span::raw(if let Some(progress) = &app.progress {
+            if let Some((done, total)) = progress.done {
+                format!("{} {}/{}", progress.label, done, total)
+            } else {
+                format!("{} {}", SPINNER_FRAMES[app.spinner_frame], progress.label)
+            }
+        } else {
+            String::new()
+        }),
+    );
+
+    // Status paragraph
+    let mut status_format = if let Some(format) = app.config.status_format.clone() {
+        format
+    } else {
+        Config::default().status_format.unwrap()
+    };
+    // In compact mode there is no title, the version goes in the status unless the format already shows it
+    if compact && !status_format.contains("{version}") {
+        status_format = format!("{{version}}    {}", status_format);
+    }
+    let status_paragraph = Paragraph::new(format_status(&status_format, &placeholders))
+        .wrap(Wrap { trim: true })
+        .block(status_bar)
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+    f.render_widget(status_paragraph, top_chunks[0]);
+
+    // If the command view is open, render it
+    if let AppMode::CommandMode = app.mode {
+        // Borders of the commands view
+        let (commands_view_borders, commands_view_border_type) = app.colors.commands_view_borders;
+
+        let command_view = Block::default()
+            .title("Commands")
+            .border_style(Style::default().fg(app.colors.active_view_border))
+            .borders(commands_view_borders)
+            .border_type(commands_view_border_type)
+            .style(Style::default().bg(app.colors.commands_view_background));
+
+        let command_paragraph =
+            Paragraph::new(format!("> {}", app.command_buffer)).block(command_view);
+
+        f.render_widget(command_paragraph, bottom_chunks[1]);
+        // The cursor goes after the displayed width of the buffer, not its length in bytes
+        f.set_cursor(
+            bottom_chunks[1].x + app.command_buffer.width() as u16 + 3,
+            bottom_chunks[1].y + 1,
+        );
+    }
+
+    // Borders of the editor
+    let (editor_borders, editor_border_type) = app.colors.editor_borders;
+
+    // Editor block
+    let editor = Block::default()
+        .border_style(Style::default().fg(if app.is_focused(Pane::Editor) {
+            app.colors.active_view_border
+        } else {
+            app.colors.view_border
+        }))
+        .borders(editor_borders)
+        .title("Editor")
+        .border_type(editor_border_type)
+        .style(Style::default().bg(app.colors.editor_background));
+
+    // If the terminal pane is open, give it the bottom of the editor area
+    let editor_chunks = Layout::default()
+        .constraints(if app.terminal.is_some() {
+            [Constraint::Percentage(65), Constraint::Percentage(35)]
+        } else {
+            [Constraint::Percentage(100), Constraint::Percentage(0)]
+        })
+        .direction(Direction::Vertical)
+        .split(chunks[1]);
+
+    // If there is no workspace, no open file and nothing has been written, render the start screen inside the editor
+    let insert_mode = matches!(app.mode, AppMode::InsertMode);
+    if app.working_path.is_none()
+        && app.buffer.path.is_none()
+        && app.buffer.is_empty()
+        && !insert_mode
+    {
+        let welcome = Paragraph::new(welcome_screen(&app.keybindings))
+            .block(editor)
+            .style(Style::default().fg(app.colors.app_foreground))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(welcome, editor_chunks[0]);
+    } else {
+        let area = editor_chunks[0];
+        let height = area.height.saturating_sub(2) as usize;
+        app.buffer.scroll_to_cursor(height);
+
+        // Only the visible lines are joined, not the whole file
+        let text = Paragraph::new(app.buffer.visible_lines(height).join("\n"))
+            .block(editor)
+            .style(Style::default().fg(app.colors.app_foreground));
+        f.render_widget(text, area);
+
+        // Show the cursor when the editor has the focus, placed after the displayed width of the text before it
+        if app.is_focused(Pane::Editor) && !app.show_dialog && !app.show_palette {
+            let x = area.x + 1 + app.buffer.before_cursor().width() as u16;
+            let y = area.y + 1 + (app.buffer.cursor.0 - app.buffer.scroll) as u16;
+            f.set_cursor(x.min(area.right().saturating_sub(2)), y);
+        }
+    }
+
+    // If the terminal pane is open, render the last lines of the output
+    if let Some(terminal) = &app.terminal {
+        let title = match terminal.exit {
+            None => format!("Terminal: {}", terminal.command),
+            Some(Some(code)) => format!("Terminal: {} (exited with {})", terminal.command, code),
+            Some(None) => format!("Terminal: {} (terminated)", terminal.command),
+        };
+        let height = editor_chunks[1].height.saturating_sub(2) as usize;
+        let lines = terminal.lines.lock().unwrap();
+        let output = Paragraph::new(
+            lines
+                .iter()
+                .skip(lines.len().saturating_sub(height))
+                .cloned()
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )
+        .style(
+            Style::default()
+                .bg(app.colors.commands_view_background)
+                .fg(app.colors.commands_view_foreground),
+        )
+        .block(
+            Block::default()
+                .title(title)
+                .border_style(Style::default().fg(app.colors.view_border))
+                .borders(Borders::ALL),
+        );
+
+        f.render_widget(output, editor_chunks[1]);
+    }
+
+    // If the palette is open, render it over everything else
+    if app.show_palette {
+        let area = Rect {
+            x: (size.x + (size.width / 2)) - (size.width / 2) / 2,
+            y: (size.y + (size.height / 2)) - (size.height / 2) / 2,
+            height: size.height / 2,
+            width: size.width / 2,
+        };
+        let palette_chunks = Layout::default()
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .direction(Direction::Vertical)
+            .split(area);
+
+        let palette_style = Style::default()
+            .bg(app.colors.commands_view_background)
+            .fg(app.colors.commands_view_foreground);
+        let palette_border = Style::default().fg(app.colors.active_view_border);
+
+        // Filter typed by the user
+        let filter = Paragraph::new(format!("> {}", app.palette_filter))
+            .style(palette_style)
+            .block(
+                Block::default()
+                    .title("Command Palette")
+                    .border_style(palette_border)
+                    .borders(Borders::ALL),
+            );
+
+        // Commands matching the filter
+        let commands: Vec<ListItem> = app
+            .palette
+            .items
+            .iter()
+            .map(|(name, description)| {
+                ListItem::new(Spans::from(vec![
+                    Span::styled(name.clone(), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(format!("  {}", description)),
+                ]))
+            })
+            .collect();
+        let commands = List::new(commands)
+            .style(palette_style)
+            .block(
+                Block::default()
+                    .border_style(palette_border)
+                    .borders(Borders::ALL),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(app.colors.explorer_selected_background)
+                    .fg(app.colors.explorer_selected_foreground)
+                    .add_modifier(app.colors.selected_modifier),
+            );
+
+        f.render_widget(Clear, area);
+        f.render_widget(filter, palette_chunks[0]);
+        f.render_stateful_widget(commands, palette_chunks[1], &mut app.palette.state);
+    }
+
+    // If a dialog is open, render it on top of the panes
+    if app.show_dialog {
+        // Borders of the dialog
+        let (dialog_borders, dialog_border_type) = app.colors.dialog_borders;
+
+        // Block of the dialog
+        let dialog_block = Block::default()
+            .title(app.dialog_title.clone())
+            .border_style(Style::default().fg(app.colors.dialog_border))
+            .border_type(dialog_border_type)
+            .borders(dialog_borders);
+
+        // Block of the "continue" text
+        let continue_block = Block::default().borders(Borders::NONE);
+
+        let dialog_area = Rect {
+            x: (size.x + (size.width / 2)) - (size.width / 2) / 2,
+            y: (size.y + (size.height / 2)) - (size.height / 2) / 2,
+            height: size.height / 2,
+            width: size.width / 2,
+        };
+
+        // The height of the dialog is only known here, scroll it so that its last line is at the bottom
+        if app.dialog_scroll_to_end {
+            let height = dialog_block.inner(dialog_area).height as usize;
+            app.dialog_scroll = app
+                .dialog_content
+                .lines()
+                .count()
+                .saturating_sub(height)
+                .min(u16::MAX as usize) as u16;
+            app.dialog_scroll_to_end = false;
+        }
+
+        let dialog_paragraph = Paragraph::new(app.dialog_content.clone())
+            .block(dialog_block)
+            .alignment(Alignment::Center)
+            .scroll((app.dialog_scroll, 0));
+
+        let dialog_chunks = Layout::default()
+            .constraints([Constraint::Percentage(90), Constraint::Percentage(10)])
+            .direction(Direction::Vertical)
+            .split(dialog_area);
+
+        f.render_widget(Clear, dialog_area);
+        f.render_widget(dialog_paragraph, dialog_area);
+        f.render_widget(
+            Paragraph::new(dialog_hint(&app.keybindings, app.pending_action.is_some()))
+                .block(continue_block)
+                .alignment(Alignment::Center),
+            dialog_chunks[1],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::channel::unbounded;
+    use std::fs;
+    use tui::backend::TestBackend;
+
+    // Default configuration logging in the temporary directory instead of the user's logs
+    fn test_config() -> Config {
+        Config {
+            logs_directory: Some(
+                std::env::temp_dir()
+                    .join("ledit-test-logs")
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            ..Config::default()
+        }
+    }
+
+    // Flatten the explorer tree into the list used for the selection, like `render` does
+    fn flatten(app: &mut App) {
+        let mut items = Vec::new();
+        app.items.items = Vec::new();
+        for node in app.file_list.nodes.clone() {
+            expand(node, &mut items, &mut app.items, &app.config, &app.colors);
+        }
+    }
+
+    // Draw a frame of the app on a test backend and return its rows
+    fn snapshot(app: &mut App) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn select(app: &mut App, path: &Path) {
+        let path = path.to_str().unwrap();
+        let ind = app.items.items.iter().position(|n| n.value == path);
+        app.items.state.select(ind);
+    }
+
+    #[test]
+    fn toggle_expansion_after_reload() {
+        let workspace = std::env::temp_dir().join(format!("ledit-explorer-{}", std::process::id()));
+        fs::create_dir_all(workspace.join("first")).unwrap();
+        fs::create_dir_all(workspace.join("second")).unwrap();
+        fs::write(workspace.join("first").join("file.txt"), "").unwrap();
+
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        app.working_path = Some(workspace.to_str().unwrap().to_string());
+        app.load_explorer().unwrap();
+
+        flatten(&mut app);
+        select(&mut app, &workspace.join("first"));
+        app.toggle_selected();
+
+        // Reload the tree while the selection still refers to the old one
+        app.load_explorer().unwrap();
+        let first = workspace.join("first");
+        let second = workspace.join("second");
+        assert_eq!(
+            app.file_list.expanded_paths(),
+            vec![first.to_str().unwrap().to_string()]
+        );
+
+        app.toggle_selected();
+        assert!(app.file_list.expanded_paths().is_empty());
+
+        flatten(&mut app);
+        select(&mut app, &second);
+        app.toggle_selected();
+        assert_eq!(
+            app.file_list.expanded_paths(),
+            vec![second.to_str().unwrap().to_string()]
+        );
+
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+
+    #[test]
+    fn project_config_cannot_bind_keys() {
+        let workspace =
+            std::env::temp_dir().join(format!("ledit-project-config-{}", std::process::id()));
+        fs::create_dir_all(&workspace).unwrap();
+        fs::write(
+            workspace.join(".ledit.toml"),
+            "explorer_width = 30\n\n[leader_bindings]\nx = \"terminal rm -rf ~\"\n\n[keybindings]\nquit = \"j\"\n",
+        )
+        .unwrap();
+
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        app.set_workspace(workspace.to_str().unwrap().to_string())
+            .unwrap();
+
+        // The other options of the project still apply
+        assert_eq!(app.config.explorer_width, Some(30));
+        assert!(app.config.leader_bindings.is_none());
+        assert!(app.config.keybindings.is_none());
+        assert!(app.keybindings.get(&Key::Char('j')).is_none());
+
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+
+    #[test]
+    fn delete_only_inside_workspace() {
+        let root = std::env::temp_dir().join(format!("ledit-delete-{}", std::process::id()));
+        let workspace = root.join("workspace");
+        fs::create_dir_all(workspace.join("sub")).unwrap();
+        fs::create_dir_all(root.join("sibling")).unwrap();
+        fs::write(workspace.join("file.txt"), "text\n").unwrap();
+
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        app.set_workspace(workspace.to_str().unwrap().to_string())
+            .unwrap();
+
+        assert!(app.delete_path("../sibling").is_err());
+        assert!(app.delete_path("sub/..").is_err());
+        assert!(app.delete_path(".").is_err());
+        assert!(app.delete_path(root.to_str().unwrap()).is_err());
+        assert!(app.rename_path("file.txt", "../moved.txt").is_err());
+        assert!(root.join("sibling").is_dir());
+        assert!(workspace.join("sub").is_dir());
+        assert!(workspace.join("file.txt").is_file());
+
+        // Deleting the open file detaches the buffer from it
+        app.open_file("file.txt");
+        app.delete_path("file.txt").unwrap();
+        assert!(app.buffer.path.is_none());
+        assert!(app.buffer.write(None).is_err());
+        assert!(!workspace.join("file.txt").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn write_without_file_and_overwrite() {
+        let workspace = std::env::temp_dir().join(format!("ledit-write-{}", std::process::id()));
+        fs::create_dir_all(&workspace).unwrap();
+        fs::write(workspace.join("first.txt"), "first\n").unwrap();
+        fs::write(workspace.join("second.txt"), "second\n").unwrap();
+
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        app.setup_commands();
+        app.execute_command(String::from("write"));
+        assert!(app.status.text.contains("no file open"));
+
+        app.set_workspace(workspace.to_str().unwrap().to_string())
+            .unwrap();
+        app.open_file("first.txt");
+        app.buffer.insert('!');
+
+        // Another existing file is only replaced after a confirmation
+        app.save(Some(String::from("second.txt")), false);
+        assert!(app.show_dialog);
+        assert_eq!(
+            fs::read_to_string(workspace.join("second.txt")).unwrap(),
+            "second\n"
+        );
+        app.save(Some(String::from("second.txt")), true);
+        assert_eq!(
+            fs::read_to_string(workspace.join("second.txt")).unwrap(),
+            "!first\n"
+        );
+
+        // A new file shows up in the explorer
+        app.save(Some(String::from("third.txt")), false);
+        let third = workspace.join("third.txt");
+        assert!(app
+            .file_list
+            .nodes
+            .iter()
+            .any(|n| n.value == third.to_str().unwrap()));
+
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+
+    #[test]
+    fn dialog_hints() {
+        let mut keybindings = HashMap::new();
+        for (name, key) in default_keybindings() {
+            keybindings.insert(parse_key(key).unwrap(), Action::from_name(name).unwrap());
+        }
+        assert_eq!(
+            dialog_hint(&keybindings, false),
+            "Press <Enter>, <Esc> or <q> to close"
+        );
+        assert_eq!(
+            dialog_hint(&keybindings, true),
+            "Press <y> to confirm, <n>, <Esc> or <q> to cancel"
+        );
+
+        // Rebound and unbound actions are shown as they are
+        keybindings.retain(|_, a| *a != Action::Confirm && *a != Action::Quit);
+        keybindings.insert(Key::Ctrl('y'), Action::Confirm);
+        assert_eq!(
+            dialog_hint(&keybindings, true),
+            "Press <Ctrl-y> to confirm, <n> or <Esc> to cancel"
+        );
+        keybindings.retain(|_, a| *a != Action::Confirm);
+        assert!(dialog_hint(&keybindings, true).starts_with("Press an unbound key to confirm"));
+    }
+
+    #[test]
+    fn palette_runs_commands_without_arguments() {
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        app.setup_commands();
+
+        // A command with arguments is put in the command view
+        app.open_palette();
+        for c in "delete".chars() {
+            app.handle_palette(Key::Char(c));
+        }
+        app.handle_palette(Key::Char('\n'));
+        assert!(!app.show_palette);
+        assert!(matches!(app.mode, AppMode::CommandMode));
+        assert_eq!(app.command_buffer, "delete ");
+
+        // A command without arguments runs directly
+        app.mode = AppMode::NormalMode;
+        app.command_buffer = String::new();
+        app.open_palette();
+        for c in "about".chars() {
+            app.handle_palette(Key::Char(c));
+        }
+        app.handle_palette(Key::Char('\n'));
+        assert!(!app.show_palette);
+        assert!(matches!(app.mode, AppMode::NormalMode));
+        assert!(app.command_buffer.is_empty());
+        assert!(matches!(
+            app.receiver.try_recv(),
+            Ok(AppEvent::ShowDialog(_))
+        ));
+
+        assert!(!takes_arguments("Quits the application.\nUsage: quit"));
+        assert!(takes_arguments("Writes the buffer.\nUsage: write [<path>]"));
+        assert!(takes_arguments("No usage"));
+    }
+
+    #[test]
+    fn monochrome_selection_is_reversed() {
+        let (tx, rx) = unbounded();
+        let config = Config {
+            monochrome: Some(true),
+            ..test_config()
+        };
+        let app = App::new(tx, rx, config).unwrap();
+        assert!(app.colors.selected_modifier.contains(Modifier::REVERSED));
+        assert_eq!(
+            app.colors.explorer_selected_background,
+            tui::style::Color::Reset
+        );
+    }
+
+    #[test]
+    fn dialog_scrolled_to_end() {
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        let lines: Vec<String> = (0..100).map(|i| format!("line {}", i)).collect();
+        app.open_dialog("Logs".to_string(), lines.join("\n"), None);
+        app.dialog_scroll_to_end = true;
+
+        let screen = snapshot(&mut app);
+        assert!(!app.dialog_scroll_to_end);
+        assert!(app.dialog_scroll > 0);
+        assert!(screen.iter().any(|l| l.contains("line 99")));
+        assert!(!screen.iter().any(|l| l.contains("line 0 ")));
+    }
+
+    #[test]
+    fn counts_are_clamped() {
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+
+        // A leading 0 is a key, not a count
+        assert_eq!(app.update_count(&Event::Input(Key::Char('0'))), 1);
+        for c in "120".chars() {
+            assert_eq!(app.update_count(&Event::Input(Key::Char(c))), 0);
+        }
+        assert_eq!(app.update_count(&Event::Input(Key::Char('j'))), 120);
+        assert_eq!(app.update_count(&Event::Input(Key::Char('j'))), 1);
+
+        for _ in 0..30 {
+            app.update_count(&Event::Input(Key::Char('9')));
+        }
+        assert_eq!(app.update_count(&Event::Input(Key::Char('j'))), MAX_COUNT);
+
+        app.dialog_content = "line\n".repeat(10);
+        app.scroll_dialog(i32::MAX);
+        assert_eq!(app.dialog_scroll, 9);
+        app.scroll_dialog(i32::MIN);
+        assert_eq!(app.dialog_scroll, 0);
+    }
+
+    #[test]
+    fn terminal_output_and_close() {
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+
+        // Only the last lines of a long output are kept
+        app.run_in_terminal(format!("seq {}", MAX_TERMINAL_LINES + 10));
+        for _ in 0..100 {
+            app.poll_terminal();
+            if app.terminal.as_ref().unwrap().exit.is_some() {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(50));
+        }
+        let terminal = app.terminal.as_ref().unwrap();
+        assert_eq!(terminal.exit, Some(Some(0)));
+        assert!(app.progress.is_none());
+        thread::sleep(std::time::Duration::from_millis(100));
+        let lines = terminal.lines.lock().unwrap();
+        assert_eq!(lines.len(), MAX_TERMINAL_LINES);
+        assert_eq!(
+            lines.back().unwrap(),
+            &(MAX_TERMINAL_LINES + 10).to_string()
+        );
+        drop(lines);
+
+        // Closing the pane stops a command still running
+        app.run_in_terminal(String::from("sleep 30"));
+        assert!(app.progress.is_some());
+        app.close_terminal();
+        assert!(app.terminal.is_none());
+        assert!(app.progress.is_none());
+    }
+
+    // Text of the status built from the given format
+    fn status_text(format: &str) -> String {
+        let mut placeholders = HashMap::new();
+        placeholders.insert("mode", Span::raw("Normal"));
+        placeholders.insert("status", Span::raw("Saved"));
+        placeholders.insert("empty", Span::raw(""));
+        format_status(format, &placeholders)
+            .0
+            .iter()
+            .map(|s| s.content.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn status_placeholders() {
+        assert_eq!(status_text(""), "");
+        assert_eq!(status_text("no placeholders"), "no placeholders");
+        assert_eq!(status_text("Mode: {mode}"), "Mode: Normal");
+        assert_eq!(status_text("{mode}{status}"), "NormalSaved");
+        assert_eq!(status_text("[{empty}]"), "[]");
+        assert_eq!(status_text("é {mode} ü"), "é Normal ü");
+        // Unknown and unclosed placeholders stay as they are
+        assert_eq!(status_text("{unknown} {mode}"), "{unknown} Normal");
+        assert_eq!(status_text("{mode"), "{mode");
+        assert_eq!(status_text("{mode} {"), "Normal {");
+        assert_eq!(status_text("}{}"), "}{}");
+        assert_eq!(status_text("{{mode}}"), "{Normal}");
+        assert_eq!(status_text("{a{mode}"), "{aNormal");
+    }
+
+    #[test]
+    fn draw_start_screen() {
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        let rows = snapshot(&mut app);
+
+        assert!(rows[0].contains(&format!("LEdit v{}", env!("CARGO_PKG_VERSION"))));
+        assert!(rows.iter().any(|r| r.contains("Current Mode: Normal Mode")));
+        assert!(rows.iter().any(|r| r.contains("Explorer")));
+        assert!(rows.iter().any(|r| r.contains("Editor")));
+        assert!(rows.iter().any(|r| r.contains("Welcome to LEdit")));
+
+        // An empty file opened without a workspace replaces the start screen
+        let file = std::env::temp_dir().join(format!("ledit-empty-{}.txt", std::process::id()));
+        fs::write(&file, "").unwrap();
+        app.open_file(file.to_str().unwrap());
+        let rows = snapshot(&mut app);
+        assert!(!rows.iter().any(|r| r.contains("Welcome to LEdit")));
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn warn_about_invalid_log_format() {
+        let (tx, rx) = unbounded();
+        let config = Config {
+            log_format: Some("xml".to_string()),
+            ..test_config()
+        };
+        let app = App::new(tx, rx, config).unwrap();
+        assert!(matches!(app.status.level, StatusLevel::WARNING));
+        assert!(app.status.text.contains("Invalid log format xml"));
+    }
+
+    #[test]
+    fn compact_status_shows_version() {
+        let (tx, rx) = unbounded();
+        let config = Config {
+            compact: Some(true),
+            ..test_config()
+        };
+        let mut app = App::new(tx, rx, config).unwrap();
+        let rows = snapshot(&mut app);
+        let version = format!("LEdit v{}", env!("CARGO_PKG_VERSION"));
+
+        let status = format!("{}    Current Mode: Normal Mode", version);
+        assert!(rows.iter().any(|r| r.contains(&status)));
+        assert_eq!(rows.iter().filter(|r| r.contains(&version)).count(), 1);
+    }
+
+    #[test]
+    fn invalid_and_removed_workspaces() {
+        let workspace = std::env::temp_dir().join(format!("ledit-removed-{}", std::process::id()));
+        fs::create_dir_all(&workspace).unwrap();
+
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        let path = workspace.to_str().unwrap().to_string();
+        app.set_workspace(path.clone()).unwrap();
+
+        // A mistyped path keeps the current workspace
+        assert!(app
+            .set_workspace(workspace.join("missing").to_str().unwrap().to_string())
+            .is_err());
+        assert_eq!(app.working_path, Some(path));
+        app.check_workspace();
+        assert!(app.working_path.is_some());
+
+        // A removed workspace is closed and the explorer shows that it is empty
+        fs::remove_dir_all(&workspace).unwrap();
+        app.check_workspace();
+        assert!(app.working_path.is_none());
+        assert!(app.status.text.contains("doesn't exist anymore"));
+        assert_eq!(app.file_list.nodes.len(), 1);
+        assert!(matches!(app.file_list.nodes[0].node_type, NodeType::Info));
+    }
+
+    #[test]
+    fn create_only_inside_workspace() {
+        let root = std::env::temp_dir().join(format!("ledit-create-{}", std::process::id()));
+        let workspace = root.join("workspace");
+        fs::create_dir_all(&workspace).unwrap();
+
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        assert!(app.create_path("file.txt").is_err());
+        app.set_workspace(workspace.to_str().unwrap().to_string())
+            .unwrap();
+
+        assert!(app.create_path("../outside.txt").is_err());
+        assert!(app.create_path("missing/../../outside.txt").is_err());
+        assert!(app
+            .create_path(root.join("outside.txt").to_str().unwrap())
+            .is_err());
+        assert!(!root.join("outside.txt").exists());
+
+        // Missing parents inside the workspace are created
+        app.create_path("a/b/file.txt").unwrap();
+        assert!(workspace.join("a/b/file.txt").is_file());
+        app.create_path("c/d/").unwrap();
+        assert!(workspace.join("c/d").is_dir());
+        assert!(app.create_path("a/b/file.txt").is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn keybindings_fallback_and_conflicts() {
+        let mut config = test_config();
+        let mut keybindings = HashMap::new();
+        keybindings.insert("quit".to_string(), "ctl-q".to_string());
+        keybindings.insert("command_mode".to_string(), "f".to_string());
+        keybindings.insert("insert_mode".to_string(), "e".to_string());
+        config.keybindings = Some(keybindings);
+
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, config).unwrap();
+
+        // The unparseable key falls back to the default one
+        assert!(app.keybindings.get(&Key::Char('q')) == Some(&Action::Quit));
+        // `f` stays with the explorer, which comes first in the defaults
+        assert!(app.keybindings.get(&Key::Char('f')) == Some(&Action::ToggleExplorer));
+        assert!(app.keybindings.get(&Key::Char('e')) == Some(&Action::InsertMode));
+        assert!(app.keybindings.get(&Key::Char('i')).is_none());
+        assert!(!app.keybindings.values().any(|a| *a == Action::CommandMode));
+        assert!(app.status.text.contains("quit = \"ctl-q\""));
+        assert!(app
+            .status
+            .text
+            .contains("f is bound to both toggle_explorer and command_mode"));
+
+        let rows = snapshot(&mut app);
+        assert!(rows.iter().any(|r| r.contains("e  Enter insert mode")));
+        assert!(rows
+            .iter()
+            .any(|r| r.contains("unbound  Enter command mode")));
+    }
+
+    #[test]
+    fn draw_palette_over_panes() {
+        let (tx, rx) = unbounded();
+        let mut app = App::new(tx, rx, test_config()).unwrap();
+        app.setup_commands();
+        app.open_palette();
+        app.handle_palette(Key::Char('a'));
+        app.handle_palette(Key::Char('b'));
+        let rows = snapshot(&mut app);
+
+        assert!(rows.iter().any(|r| r.contains("Command Palette")));
+        assert!(rows.iter().any(|r| r.contains("> ab")));
+        assert!(rows.iter().any(|r| r.contains("about")));
+        assert!(!rows.iter().any(|r| r.contains("quit")));
+    }
+}
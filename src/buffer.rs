@@ -0,0 +1,288 @@
+use std::{fs, io, path::PathBuf};
+
+// Text edited in the editor view, the cursor is a (row, col) position where `col` counts chars, not bytes
+pub struct Buffer {
+    pub lines: Vec<String>,
+    pub cursor: (usize, usize),
+    pub scroll: usize,
+    pub dirty: bool,
+    pub path: Option<PathBuf>,
+    trailing_newline: bool,
+}
+
+impl Buffer {
+    pub fn new() -> Self {
+        Buffer {
+            lines: vec![String::new()],
+            cursor: (0, 0),
+            scroll: 0,
+            dirty: false,
+            path: None,
+            trailing_newline: true,
+        }
+    }
+
+    // Load the file at the given path, fails if it can't be read or it is not valid UTF-8
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let mut content = fs::read_to_string(&path)?;
+        // The final new line doesn't start a line of its own, it is added back when writing
+        let trailing_newline = content.ends_with('\n');
+        if trailing_newline {
+            content.pop();
+        }
+
+        Ok(Buffer {
+            lines: content.split('\n').map(String::from).collect(),
+            cursor: (0, 0),
+            scroll: 0,
+            dirty: false,
+            path: Some(path),
+            trailing_newline,
+        })
+    }
+
+    // Write the buffer to its file, or to the given path which becomes the file of the buffer
+    pub fn write(&mut self, path: Option<PathBuf>) -> io::Result<PathBuf> {
+        let path = match path.or_else(|| self.path.clone()) {
+            Some(p) => p,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "no file open")),
+        };
+
+        let mut content = self.lines.join("\n");
+        if self.trailing_newline {
+            content.push('\n');
+        }
+        fs::write(&path, content)?;
+
+        self.path = Some(path.clone());
+        self.dirty = false;
+        Ok(path)
+    }
+
+    // Check if the buffer has no text at all
+    pub fn is_empty(&self) -> bool {
+        self.lines.len() == 1 && self.lines[0].is_empty()
+    }
+
+    // Byte index in the current line of the char at the cursor
+    fn byte_index(&self) -> usize {
+        let (row, col) = self.cursor;
+        self.lines[row]
+            .char_indices()
+            .nth(col)
+            .map(|(i, _)| i)
+            .unwrap_or(self.lines[row].len())
+    }
+
+    fn line_length(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    // The part of the current line before the cursor
+    pub fn before_cursor(&self) -> &str {
+        &self.lines[self.cursor.0][..self.byte_index()]
+    }
+
+    pub fn insert(&mut self, c: char) {
+        if c == '\n' {
+            return self.new_line();
+        }
+        let index = self.byte_index();
+        self.lines[self.cursor.0].insert(index, c);
+        self.cursor.1 += 1;
+        self.dirty = true;
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        for c in text.chars() {
+            self.insert(c);
+        }
+    }
+
+    // Split the current line at the cursor
+    pub fn new_line(&mut self) {
+        let index = self.byte_index();
+        let rest = self.lines[self.cursor.0].split_off(index);
+        self.lines.insert(self.cursor.0 + 1, rest);
+        self.cursor = (self.cursor.0 + 1, 0);
+        self.dirty = true;
+    }
+
+    // Remove the char before the cursor, at the start of a line join it to the previous one
+    pub fn backspace(&mut self) {
+        let (row, col) = self.cursor;
+        if col > 0 {
+            self.cursor.1 -= 1;
+            let index = self.byte_index();
+            self.lines[row].remove(index);
+            self.dirty = true;
+        } else if row > 0 {
+            let line = self.lines.remove(row);
+            let length = self.line_length(row - 1);
+            self.lines[row - 1].push_str(&line);
+            self.cursor = (row - 1, length);
+            self.dirty = true;
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if self.cursor.1 > 0 {
+            self.cursor.1 -= 1;
+        } else if self.cursor.0 > 0 {
+            self.cursor.0 -= 1;
+            self.cursor.1 = self.line_length(self.cursor.0);
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.cursor.1 < self.line_length(self.cursor.0) {
+            self.cursor.1 += 1;
+        } else if self.cursor.0 + 1 < self.lines.len() {
+            self.cursor = (self.cursor.0 + 1, 0);
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.cursor.0 > 0 {
+            self.cursor.0 -= 1;
+            self.cursor.1 = self.cursor.1.min(self.line_length(self.cursor.0));
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.cursor.0 + 1 < self.lines.len() {
+            self.cursor.0 += 1;
+            self.cursor.1 = self.cursor.1.min(self.line_length(self.cursor.0));
+        }
+    }
+
+    // Scroll so that the cursor is inside a view of the given height
+    pub fn scroll_to_cursor(&mut self, height: usize) {
+        if self.cursor.0 < self.scroll {
+            self.scroll = self.cursor.0;
+        } else if height > 0 && self.cursor.0 >= self.scroll + height {
+            self.scroll = self.cursor.0 + 1 - height;
+        }
+    }
+
+    // The lines shown in a view of the given height, from the scroll position
+    pub fn visible_lines(&self, height: usize) -> &[String] {
+        let start = self.scroll.min(self.lines.len());
+        let end = self.scroll.saturating_add(height).min(self.lines.len());
+        &self.lines[start..end]
+    }
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Buffer holding the given lines, with the cursor at the given position
+    fn buffer(lines: &[&str], cursor: (usize, usize)) -> Buffer {
+        let mut buffer = Buffer::new();
+        buffer.lines = lines.iter().map(|l| l.to_string()).collect();
+        buffer.cursor = cursor;
+        buffer
+    }
+
+    #[test]
+    fn visible_lines_follow_scroll() {
+        let mut b = buffer(&["a", "b", "c", "d", "e"], (0, 0));
+        assert_eq!(b.visible_lines(2), ["a", "b"]);
+        b.cursor = (3, 0);
+        b.scroll_to_cursor(2);
+        assert_eq!(b.visible_lines(2), ["c", "d"]);
+        assert_eq!(b.visible_lines(10), ["c", "d", "e"]);
+        assert!(b.visible_lines(0).is_empty());
+        b.scroll = 10;
+        assert!(b.visible_lines(2).is_empty());
+    }
+
+    #[test]
+    fn byte_index_counts_chars() {
+        let b = buffer(&["héllo wörld"], (0, 0));
+        assert_eq!(b.byte_index(), 0);
+        let b = buffer(&["héllo wörld"], (0, 2));
+        assert_eq!(b.byte_index(), 3);
+        assert_eq!(b.before_cursor(), "hé");
+        // At the end of the line the index is the length in bytes
+        let b = buffer(&["日本"], (0, 2));
+        assert_eq!(b.byte_index(), 6);
+        assert_eq!(b.line_length(0), 2);
+    }
+
+    #[test]
+    fn insert_multibyte() {
+        let mut b = buffer(&["日本"], (0, 1));
+        b.insert('é');
+        b.insert_str("ü\nß");
+        assert_eq!(b.lines, vec!["日éü", "ß本"]);
+        assert_eq!(b.cursor, (1, 1));
+        assert!(b.dirty);
+    }
+
+    #[test]
+    fn new_line_mid_multibyte_line() {
+        let mut b = buffer(&["añob"], (0, 2));
+        b.new_line();
+        assert_eq!(b.lines, vec!["añ", "ob"]);
+        assert_eq!(b.cursor, (1, 0));
+    }
+
+    #[test]
+    fn backspace_joins_lines() {
+        let mut b = buffer(&["çà", "日本"], (1, 0));
+        b.backspace();
+        assert_eq!(b.lines, vec!["çà日本"]);
+        assert_eq!(b.cursor, (0, 2));
+
+        b.backspace();
+        assert_eq!(b.lines, vec!["ç日本"]);
+        assert_eq!(b.cursor, (0, 1));
+
+        // Nothing before the start of the buffer
+        let mut b = buffer(&["é"], (0, 0));
+        b.backspace();
+        assert_eq!(b.lines, vec!["é"]);
+        assert!(!b.dirty);
+    }
+
+    #[test]
+    fn cursor_stays_in_lines() {
+        let mut b = buffer(&["ééé", "é"], (0, 3));
+        b.move_down();
+        assert_eq!(b.cursor, (1, 1));
+        b.move_right();
+        assert_eq!(b.cursor, (1, 1));
+        b.move_left();
+        b.move_left();
+        assert_eq!(b.cursor, (0, 3));
+    }
+
+    #[test]
+    fn write_keeps_trailing_newline() {
+        let dir = std::env::temp_dir().join(format!("ledit-buffer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        for content in ["ünïcode\nline\n", "no newline", "", "\n", "two\n\n"].iter() {
+            let path = dir.join("file.txt");
+            fs::write(&path, content).unwrap();
+            let mut b = Buffer::open(path.clone()).unwrap();
+            b.dirty = true;
+            b.write(None).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), *content);
+            assert!(!b.dirty);
+        }
+
+        let mut b = Buffer::new();
+        assert!(b.write(None).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,14 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
 
 use async_std::channel::Sender;
 use futures::executor::block_on;
 
-use crate::util::{AppEvent, Status};
+use crate::util::{AppEvent, Progress, Status};
 
 pub trait Command {
     fn get_name(&self) -> String;
@@ -18,6 +23,20 @@ pub enum CommandError {
     ExecutionError(Option<String>),
 }
 
+// Send an event to the application, a failure is reported as an execution error
+fn send_event(
+    tx: &Sender<AppEvent>,
+    event: AppEvent,
+    description: &str,
+) -> Result<(), CommandError> {
+    block_on(tx.send(event)).map_err(|e| {
+        CommandError::ExecutionError(Some(format!(
+            "Error while sending the {} event to the application: {}",
+            description, e
+        )))
+    })
+}
+
 pub struct CommandParser {
     pub commands: Vec<Box<dyn Command>>,
     transmitter: Sender<AppEvent>,
@@ -31,31 +50,34 @@ impl CommandParser {
         }
     }
 
+    // Replace the sender given to the commands, used when the channel is recreated
+    pub fn set_transmitter(&mut self, transmitter: Sender<AppEvent>) {
+        self.transmitter = transmitter;
+    }
+
     pub fn add_command(&mut self, command: Box<dyn Command>) {
         self.commands.push(command);
     }
 
+    // Find the command named by the first word of the buffer, either by its name or an alias
     pub fn parse(
-        &mut self,
+        &self,
         buffer: String,
     ) -> Result<(&Box<dyn Command>, Sender<AppEvent>), CommandError> {
-        let mut splitted: Vec<String> = buffer.split(' ').map(|p| String::from(p)).collect();
+        let name = buffer.split(' ').next().unwrap_or("");
         for cmd in self.commands.iter() {
-            if splitted[0] == cmd.get_name() {
-                &splitted.remove(0);
-                return Ok((&Box::new(cmd), self.transmitter.clone()));
-            } else {
-                for alias in cmd.get_aliases().iter() {
-                    if splitted[0] == *alias {
-                        &splitted.remove(0);
-                        return Ok((&Box::new(cmd), self.transmitter.clone()));
-                    }
-                }
+            if name == cmd.get_name() || cmd.get_aliases().iter().any(|alias| name == *alias) {
+                return Ok((cmd, self.transmitter.clone()));
             }
         }
 
         Err(CommandError::NotFound)
     }
+
+    // Arguments of the command in the buffer, the words after its name
+    pub fn arguments(buffer: &str) -> Vec<String> {
+        buffer.split(' ').skip(1).map(String::from).collect()
+    }
 }
 
 pub struct QuitCommand;
@@ -70,11 +92,7 @@ impl Command for QuitCommand {
     }
 
     fn execute(&self, tx: Sender<AppEvent>, _args: &Vec<String>) -> Result<(), CommandError> {
-        if let Err(_) = block_on(tx.send(AppEvent::Close)) {
-            return Err(CommandError::ExecutionError(Some(
-                "Error while sending the quit event to the application".to_string(),
-            )));
-        }
+        send_event(&tx, AppEvent::Close, "quit")?;
 
         Ok(())
     }
@@ -96,21 +114,336 @@ impl Command for OpenCommand {
     }
 
     fn execute(&self, tx: Sender<AppEvent>, args: &Vec<String>) -> Result<(), CommandError> {
-        if args.len() < 1 {
+        if args.is_empty() {
+            return Err(CommandError::InvalidSyntax);
+        }
+
+        // Loading the explorer blocks the app, the spinner shows that it is working
+        send_event(
+            &tx,
+            AppEvent::SetProgress(Some(Progress {
+                label: format!("Opening {}", args[0]),
+                done: None,
+            })),
+            "progress",
+        )?;
+        send_event(&tx, AppEvent::SetWorkspace(args[0].clone()), "workspace")?;
+        send_event(&tx, AppEvent::SetProgress(None), "progress")?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Set the current workspace to the given one.\nUsage: open <directory>".to_string()
+    }
+}
+
+pub struct PwdCommand;
+
+impl Command for PwdCommand {
+    fn get_name(&self) -> String {
+        String::from("pwd")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, _args: &Vec<String>) -> Result<(), CommandError> {
+        send_event(&tx, AppEvent::ShowWorkspace, "workspace")?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Show the path of the current workspace and of the open file.\nUsage: pwd".to_string()
+    }
+}
+
+pub struct RefreshCommand;
+
+impl Command for RefreshCommand {
+    fn get_name(&self) -> String {
+        String::from("refresh")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, _args: &Vec<String>) -> Result<(), CommandError> {
+        send_event(&tx, AppEvent::RefreshExplorer, "explorer refresh")?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Reload the explorer from the disk, keeping the expanded directories.\nUsage: refresh"
+            .to_string()
+    }
+}
+
+pub struct LogsCommand;
+
+impl Command for LogsCommand {
+    fn get_name(&self) -> String {
+        String::from("logs")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, _args: &Vec<String>) -> Result<(), CommandError> {
+        send_event(&tx, AppEvent::ShowLogs, "logs")?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Show the last lines of the logs, use the arrows to scroll.\nUsage: logs".to_string()
+    }
+}
+
+pub struct PaletteCommand;
+
+impl Command for PaletteCommand {
+    fn get_name(&self) -> String {
+        String::from("palette")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![String::from("p")]
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, _args: &Vec<String>) -> Result<(), CommandError> {
+        send_event(&tx, AppEvent::ShowPalette, "palette")?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Open the command palette, also available with <Ctrl-p>.\nUsage: palette".to_string()
+    }
+}
+
+pub struct NewCommand;
+
+impl Command for NewCommand {
+    fn get_name(&self) -> String {
+        String::from("new")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![String::from("n")]
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, args: &Vec<String>) -> Result<(), CommandError> {
+        if args.first().map_or(true, |a| a.is_empty()) {
+            return Err(CommandError::InvalidSyntax);
+        }
+
+        send_event(&tx, AppEvent::CreatePath(args[0].clone()), "new path")?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Create an empty file in the workspace, a path ending with / creates a directory.\nUsage: new <path>"
+            .to_string()
+    }
+}
+
+pub struct DeleteCommand;
+
+impl Command for DeleteCommand {
+    fn get_name(&self) -> String {
+        String::from("delete")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![String::from("rm")]
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, args: &Vec<String>) -> Result<(), CommandError> {
+        if args.first().map_or(true, |a| a.is_empty()) {
+            return Err(CommandError::InvalidSyntax);
+        }
+
+        // Nothing is deleted until the user confirms
+        send_event(
+            &tx,
+            AppEvent::ShowConfirm((
+                "Delete".to_string(),
+                format!("Delete {}? This can't be undone", args[0]),
+                Box::new(AppEvent::DeletePath(args[0].clone())),
+            )),
+            "delete confirmation",
+        )?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Delete a file or a directory of the workspace, after a confirmation.\nUsage: delete <path>"
+            .to_string()
+    }
+}
+
+pub struct RenameCommand;
+
+impl Command for RenameCommand {
+    fn get_name(&self) -> String {
+        String::from("rename")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![String::from("mv")]
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, args: &Vec<String>) -> Result<(), CommandError> {
+        if args.len() < 2 || args[0].is_empty() || args[1].is_empty() {
+            return Err(CommandError::InvalidSyntax);
+        }
+
+        send_event(
+            &tx,
+            AppEvent::RenamePath((args[0].clone(), args[1].clone())),
+            "rename",
+        )?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Rename or move a file or a directory of the workspace.\nUsage: rename <old> <new>"
+            .to_string()
+    }
+}
+
+pub struct TemplateCommand;
+
+impl Command for TemplateCommand {
+    fn get_name(&self) -> String {
+        String::from("template")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![String::from("tp")]
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, args: &Vec<String>) -> Result<(), CommandError> {
+        // Without arguments list the available templates
+        if args.first().map_or(true, |a| a.is_empty()) {
+            let mut templates: Vec<String> = Vec::new();
+            if let Ok(dir) = shellexpand::full("~/.ledit/templates") {
+                if let Ok(entries) = fs::read_dir(PathBuf::from(&*dir)) {
+                    for entry in entries.flatten() {
+                        templates.push(entry.file_name().to_string_lossy().to_string());
+                    }
+                }
+            }
+            templates.sort();
+
+            send_event(
+                &tx,
+                AppEvent::ShowDialog((
+                    "Templates".to_string(),
+                    if templates.is_empty() {
+                        "No templates found in ~/.ledit/templates".to_string()
+                    } else {
+                        templates.join("\n")
+                    },
+                )),
+                "dialog",
+            )?;
+
+            return Ok(());
+        }
+
+        if args.len() < 2 {
             return Err(CommandError::InvalidSyntax);
         }
 
-        if let Err(_) = block_on(tx.send(AppEvent::SetWorkspace(args[0].clone()))) {
+        send_event(
+            &tx,
+            AppEvent::NewFromTemplate((args[0].clone(), args[1].clone())),
+            "template",
+        )?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Create a file from a template of ~/.ledit/templates, {{filename}} and {{date}} are replaced.\nUsage: template [<name> <path>]".to_string()
+    }
+}
+
+pub struct TerminalCommand;
+
+impl Command for TerminalCommand {
+    fn get_name(&self) -> String {
+        String::from("terminal")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![String::from("term")]
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, args: &Vec<String>) -> Result<(), CommandError> {
+        let command = args.join(" ");
+
+        // Without a command close the terminal pane
+        if command.trim().is_empty() {
+            send_event(&tx, AppEvent::CloseTerminal, "terminal")?;
+        } else {
+            send_event(&tx, AppEvent::RunInTerminal(command), "terminal")?;
+        }
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Run a shell command in the workspace and show its output in the terminal pane, without a command close the pane.\nUsage: terminal [<command>]".to_string()
+    }
+}
+
+pub struct WriteCommand {
+    // Path of the file open in the editor, kept up to date by the app
+    current_file: Arc<RwLock<Option<PathBuf>>>,
+}
+
+impl WriteCommand {
+    pub fn new(current_file: Arc<RwLock<Option<PathBuf>>>) -> Self {
+        WriteCommand { current_file }
+    }
+}
+
+impl Command for WriteCommand {
+    fn get_name(&self) -> String {
+        String::from("write")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![String::from("w")]
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, args: &Vec<String>) -> Result<(), CommandError> {
+        let path = args.iter().find(|a| !a.is_empty()).cloned();
+        let file_open = self.current_file.read().map_or(false, |p| p.is_some());
+        if path.is_none() && !file_open {
             return Err(CommandError::ExecutionError(Some(
-                "Error while sending workspace event to the application".to_string(),
+                "no file open".to_string(),
             )));
         }
+        send_event(&tx, AppEvent::Save(path), "save")?;
 
         Ok(())
     }
 
     fn get_description(&self) -> String {
-        "Set the current workspace to the given one.\nUsage: open <directory>".to_string()
+        "Write the editor buffer to its file, or to the given path.\nUsage: write [<path>]"
+            .to_string()
     }
 }
 
@@ -142,32 +475,32 @@ impl Command for HelpCommand {
     }
 
     fn execute(&self, tx: Sender<AppEvent>, args: &Vec<String>) -> Result<(), CommandError> {
-        if args.len() < 1 {
+        if args.is_empty() {
             return Err(CommandError::InvalidSyntax);
         }
 
         if self.commands.contains_key(&args[0]) {
-            if let Err(_) = block_on(tx.send(AppEvent::ShowDialog((
-                format!("Help for {} command", args[0]),
-                if let Some(desc) = self.commands.get(&args[0]) {
-                    desc.to_string()
-                } else {
-                    "No description provided :(".to_string()
-                },
-            )))) {
-                return Err(CommandError::ExecutionError(Some(
-                    "Error while sending the dialog event to the application".to_string(),
-                )));
-            }
+            send_event(
+                &tx,
+                AppEvent::ShowDialog((
+                    format!("Help for {} command", args[0]),
+                    if let Some(desc) = self.commands.get(&args[0]) {
+                        desc.to_string()
+                    } else {
+                        "No description provided :(".to_string()
+                    },
+                )),
+                "dialog",
+            )?;
         } else {
-            if let Err(_) = block_on(tx.send(AppEvent::SetStatus(Status {
-                text: format!("{} command doesn't exist", args[0]),
-                level: crate::util::StatusLevel::ERROR,
-            }))) {
-                return Err(CommandError::ExecutionError(Some(
-                    "Error while sending the status event to the application".to_string(),
-                )));
-            }
+            send_event(
+                &tx,
+                AppEvent::SetStatus(Status {
+                    text: format!("{} command doesn't exist", args[0]),
+                    level: crate::util::StatusLevel::ERROR,
+                }),
+                "status",
+            )?;
         }
 
         Ok(())
@@ -177,3 +510,111 @@ impl Command for HelpCommand {
         "Get help for the given command\nUsage: help <command name>".to_string()
     }
 }
+
+pub struct SetCommand;
+
+impl Command for SetCommand {
+    fn get_name(&self) -> String {
+        String::from("set")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![String::from("se")]
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, args: &Vec<String>) -> Result<(), CommandError> {
+        if args.len() < 2 {
+            return Err(CommandError::InvalidSyntax);
+        }
+
+        send_event(
+            &tx,
+            AppEvent::SetOption((args[0].clone(), args[1].clone())),
+            "option",
+        )?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Change an option of the editor.\nUsage: set <option> <value>\nOptions: sort name|type|mtime, dirsfirst on|off".to_string()
+    }
+}
+
+pub struct AboutCommand;
+
+impl Command for AboutCommand {
+    fn get_name(&self) -> String {
+        String::from("about")
+    }
+
+    fn get_aliases(&self) -> Vec<String> {
+        vec![String::from("version")]
+    }
+
+    fn execute(&self, tx: Sender<AppEvent>, _args: &Vec<String>) -> Result<(), CommandError> {
+        send_event(
+            &tx,
+            AppEvent::ShowDialog((
+                "About LEdit".to_string(),
+                format!(
+                    "LEdit v{}\nBuild: {} ({}-{})\n{}",
+                    env!("CARGO_PKG_VERSION"),
+                    if cfg!(debug_assertions) {
+                        "debug"
+                    } else {
+                        "release"
+                    },
+                    std::env::consts::OS,
+                    std::env::consts::ARCH,
+                    env!("CARGO_PKG_REPOSITORY"),
+                ),
+            )),
+            "dialog",
+        )?;
+
+        Ok(())
+    }
+
+    fn get_description(&self) -> String {
+        "Show the version of LEdit.\nUsage: about".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::channel::unbounded;
+
+    #[test]
+    fn parse_commands() {
+        let (tx, rx) = unbounded();
+        let mut parser = CommandParser::new(tx);
+        parser.add_command(Box::new(QuitCommand));
+        parser.add_command(Box::new(RenameCommand));
+        let help = HelpCommand::new(&parser.commands);
+        parser.add_command(Box::new(help));
+
+        for (buffer, name) in [("quit", "quit"), ("q", "quit"), ("help open", "help")].iter() {
+            let (cmd, _) = parser.parse(buffer.to_string()).ok().unwrap();
+            assert_eq!(cmd.get_name(), *name);
+        }
+        assert!(parser.parse(String::from("nope")).is_err());
+        assert!(parser.parse(String::from("")).is_err());
+
+        assert!(CommandParser::arguments("quit").is_empty());
+        assert_eq!(CommandParser::arguments("help open"), vec!["open"]);
+
+        // The arguments reach the application in the event of the command
+        let buffer = "mv a.txt b.txt";
+        let (cmd, tx) = parser.parse(buffer.to_string()).ok().unwrap();
+        assert!(cmd.execute(tx, &CommandParser::arguments(buffer)).is_ok());
+        match rx.try_recv() {
+            Ok(AppEvent::RenamePath((from, to))) => {
+                assert_eq!(from, "a.txt");
+                assert_eq!(to, "b.txt");
+            }
+            _ => panic!("the rename event wasn't sent"),
+        }
+    }
+}
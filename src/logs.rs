@@ -1,55 +1,237 @@
 use std::{
-    fs::File,
+    collections::VecDeque,
+    fmt,
+    fs::{self, File, OpenOptions},
     io::{LineWriter, Write},
     path::PathBuf,
 };
 
-use chrono::Local;
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde_json::json;
+
+// Number of recent lines kept in memory for the log viewer
+const MAX_RECENT_LINES: usize = 500;
+
+// A logged message, the log viewer shows it as text whatever the format of the file is
+pub struct LogEntry {
+    level: &'static str,
+    time: String,
+    message: String,
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}][{}]: {}", self.level, self.time, self.message)
+    }
+}
+
+// Name of the rotated logs, from the time the log was last written
+const ROTATED_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
 
 pub struct Logger {
     logs_path: PathBuf,
-    logs: Vec<String>,
+    file: Option<LineWriter<File>>,
+    recent: VecDeque<LogEntry>,
+    format: LogFormat,
+    max_files: usize,
 }
 
 impl Logger {
-    pub fn new(logs_path: String) -> Self {
-        Logger {
+    // Open `latest.log` in the logs directory, the log of the previous session is rotated first
+    pub fn new(logs_path: String, format: LogFormat, max_files: usize) -> Self {
+        let mut logger = Logger {
             logs_path: if let Ok(path) = shellexpand::full(&logs_path) {
                 PathBuf::from(&*path)
             } else {
                 PathBuf::from(logs_path)
             },
-            logs: Vec::new(),
+            file: None,
+            recent: VecDeque::new(),
+            format,
+            max_files,
+        };
+
+        // The logs directory doesn't exist on the first run
+        let _ = fs::create_dir_all(&logger.logs_path);
+        logger.rotate();
+
+        let latest = logger.logs_path.join("latest.log");
+        match OpenOptions::new().create(true).append(true).open(&latest) {
+            Ok(file) => logger.file = Some(LineWriter::new(file)),
+            // The TUI isn't running yet, so the error can still be read on stderr
+            Err(e) => eprintln!(
+                "Cannot open {}, the logs won't be saved: {}",
+                latest.display(),
+                e
+            ),
         }
+
+        logger
     }
 
     pub fn log(&mut self, level: LogLevel, message: String) {
-        let current_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let entry = LogEntry {
+            level: match level {
+                LogLevel::ERROR => "ERROR",
+                LogLevel::WARN => "WARN",
+                LogLevel::INFO => "INFO",
+            },
+            time: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            message,
+        };
 
-        let level_str = match level {
-            LogLevel::ERROR => String::from("ERROR"),
-            LogLevel::WARN => String::from("WARN"),
-            LogLevel::INFO => String::from("INFO"),
+        let line = match self.format {
+            LogFormat::Text => entry.to_string(),
+            // One JSON object per line
+            LogFormat::Json => json!({
+                "level": entry.level,
+                "time": entry.time,
+                "message": entry.message,
+            })
+            .to_string(),
         };
 
-        self.logs
-            .push(format!("[{}][{}]: {}", level_str, current_time, message));
+        // Each line is written right away so a crash doesn't lose it, a failed write stops the file logging
+        if let Some(file) = &mut self.file {
+            if writeln!(file, "{}", line).is_err() {
+                self.file = None;
+            }
+        }
+
+        if self.recent.len() == MAX_RECENT_LINES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(entry);
     }
 
-    pub fn write(&mut self) {
-        if let Ok(file) = File::create(&self.logs_path.join("latest.log")) {
-            let mut writer = LineWriter::new(file);
-            for log in self.logs.iter() {
-                if let Err(e) = writer.write(format!("{}\n", &log).as_bytes()) {
-                    panic!("Error while trying to write the logs!: {}", e.to_string());
-                }
+    // The last logged messages, the oldest first
+    pub fn recent(&self) -> &VecDeque<LogEntry> {
+        &self.recent
+    }
+
+    // Keep the log of the previous session under the time it was last written, removing the oldest logs
+    // beyond `max_files`
+    fn rotate(&self) {
+        let latest = self.logs_path.join("latest.log");
+        if let Ok(modified) = fs::metadata(&latest).and_then(|m| m.modified()) {
+            let stem = DateTime::<Local>::from(modified)
+                .format(ROTATED_FORMAT)
+                .to_string();
+            // Sessions written in the same second get a numbered suffix instead of replacing each other
+            let mut rotated = self.logs_path.join(format!("{}.log", stem));
+            let mut suffix = 1;
+            while rotated.exists() {
+                rotated = self.logs_path.join(format!("{}_{}.log", stem, suffix));
+                suffix += 1;
+            }
+            let _ = fs::rename(&latest, rotated);
+        }
+
+        if let Ok(entries) = fs::read_dir(&self.logs_path) {
+            let mut rotated: Vec<PathBuf> = entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .map_or(false, is_rotated_name)
+                })
+                .collect();
+            // The names start with the date, so the oldest come first
+            rotated.sort();
+            while rotated.len() > self.max_files {
+                let _ = fs::remove_file(rotated.remove(0));
             }
         }
     }
 }
 
+// Check if a file name is one given by the rotation, the other files of the logs directory are never removed
+fn is_rotated_name(name: &str) -> bool {
+    let stem = match name.strip_suffix(".log") {
+        Some(s) => s,
+        None => return false,
+    };
+    // The date is 19 chars long, it can be followed by a numbered suffix
+    let (date, suffix) = match (stem.get(..19), stem.get(19..)) {
+        (Some(d), Some(s)) => (d, s),
+        _ => return false,
+    };
+    let valid_suffix = suffix.is_empty()
+        || suffix.strip_prefix('_').map_or(false, |n| {
+            !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+        });
+
+    valid_suffix && NaiveDateTime::parse_from_str(date, ROTATED_FORMAT).is_ok()
+}
+
 pub enum LogLevel {
     INFO,
     WARN,
     ERROR,
 }
+
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotated_names() {
+        assert!(is_rotated_name("2024-01-02_15-04-05.log"));
+        assert!(is_rotated_name("2024-01-02_15-04-05_3.log"));
+        assert!(!is_rotated_name("latest.log"));
+        assert!(!is_rotated_name("server.log"));
+        assert!(!is_rotated_name("2024-01-02_15-04-05.txt"));
+        assert!(!is_rotated_name("2024-01-02_15-04-05_.log"));
+        assert!(!is_rotated_name("2024-01-02_15-04-05-backup.log"));
+        assert!(!is_rotated_name("2024-13-02_15-04-05.log"));
+    }
+
+    #[test]
+    fn recent_entries_are_text() {
+        let dir = std::env::temp_dir().join(format!("ledit-recent-{}", std::process::id()));
+        let mut logger = Logger::new(dir.to_str().unwrap().to_string(), LogFormat::Json, 0);
+        logger.log(LogLevel::WARN, "Something happened".to_string());
+
+        let line = logger.recent().back().unwrap().to_string();
+        assert!(line.starts_with("[WARN]["));
+        assert!(line.ends_with("]: Something happened"));
+        // The file keeps the configured format
+        let written = fs::read_to_string(dir.join("latest.log")).unwrap();
+        assert!(written.starts_with('{'));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_keeps_other_logs() {
+        let dir = std::env::temp_dir().join(format!("ledit-rotate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("server.log"), "").unwrap();
+        fs::write(dir.join("2020-01-01_00-00-00.log"), "").unwrap();
+        fs::write(dir.join("latest.log"), "").unwrap();
+
+        // No rotated log is kept, the previous session is rotated and removed with the old one
+        Logger::new(dir.to_str().unwrap().to_string(), LogFormat::Text, 0);
+        assert!(dir.join("server.log").exists());
+        assert!(!dir.join("2020-01-01_00-00-00.log").exists());
+        assert!(dir.join("latest.log").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
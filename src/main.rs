@@ -1,7 +1,7 @@
 mod application;
+mod buffer;
 use application::render;
 use util::Config;
-use util::Status;
 mod commands;
 mod logs;
 mod util;
@@ -16,6 +16,7 @@ use std::io::Read;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::process;
 
 // Program entry point
 fn main() -> Result<(), Box<dyn Error>> {
@@ -25,15 +26,16 @@ fn main() -> Result<(), Box<dyn Error>> {
     if let Ok(path) = shellexpand::full("~/.ledit") {
         let dir = PathBuf::from(Path::new(&*path));
         if !dir.exists() {
-            if let Err(_) = fs::create_dir(&dir) {
+            if fs::create_dir(&dir).is_err() {
                 eprintln!("Error while creating the application directory!")
             }
-            if let Err(_) = fs::create_dir(dir.join("logs")) {
+            if fs::create_dir(dir.join("logs")).is_err() {
                 eprintln!("Error while creating the application logs directory!")
             }
             if let Ok(mut config_file) = File::create(dir.join("config.toml")) {
-                if let Err(_) =
-                    config_file.write_all(toml::to_string(&Config::default()).unwrap().as_bytes())
+                if config_file
+                    .write_all(toml::to_string(&Config::default()).unwrap().as_bytes())
+                    .is_err()
                 {
                     eprintln!("Error while creating the default configuration file!")
                 }
@@ -47,7 +49,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         if dir.exists() {
             let mut buf = String::new();
             if let Ok(mut file) = File::open(dir) {
-                if let Ok(_) = file.read_to_string(&mut buf) {
+                if file.read_to_string(&mut buf).is_ok() {
                     config = toml::from_str(&buf)
                         .expect("Cannot load the config file, check the syntax!");
                 }
@@ -55,7 +57,14 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+
+    // With `--strict` the app exits with an error instead of starting with an empty workspace
+    let strict = args.iter().any(|a| a == "--strict");
+    args.retain(|a| a != "--strict");
+
+    let default_workspace = config.default_workspace.clone();
+    let startup_file = config.startup_file.clone();
 
     let (tx, rx) = unbounded();
 
@@ -67,12 +76,44 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // If there is at least an argument use it as workspace folder
     if args.len() > 1 {
-        app.working_path = Some(args[1].clone());
-        if let Err(_) = app.load_explorer() {
-            app.status = Status {
-                text: format!("Failed to open the workspace from {}", args[1].clone()),
-                level: util::StatusLevel::ERROR,
+        if let Err(e) = app.set_workspace(args[1].clone()) {
+            if strict {
+                eprintln!("Failed to open the workspace from {}: {}", args[1], e);
+                process::exit(1);
+            }
+            app.notify(
+                util::StatusLevel::ERROR,
+                format!("Failed to open the workspace from {}", args[1].clone()),
+            );
+        }
+    // Else open the default workspace from the configuration, if there is one
+    } else if let Some(workspace) = default_workspace {
+        match shellexpand::full(&workspace) {
+            Ok(path) if Path::new(&*path).is_dir() => {
+                if app.set_workspace(path.to_string()).is_err() {
+                    app.notify(
+                        util::StatusLevel::ERROR,
+                        format!("Failed to open the default workspace {}", workspace),
+                    );
+                }
             }
+            _ => app.notify(
+                util::StatusLevel::WARNING,
+                format!("The default workspace {} doesn't exist", workspace),
+            ),
+        }
+    }
+
+    // Open the file from the configuration, a relative path is looked for in the workspace
+    if let Some(file) = startup_file {
+        let path = app.resolve_path(&shellexpand::tilde(&file));
+        if path.is_file() {
+            app.open_file(&path.to_string_lossy());
+        } else {
+            app.notify(
+                util::StatusLevel::WARNING,
+                format!("The startup file {} doesn't exist", file),
+            );
         }
     }
 
@@ -5,14 +5,24 @@ use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
 
-use termion::event::Key;
+use termion::event::{Event as TermEvent, Key};
 use termion::input::TermRead;
 
 pub enum Event<I> {
     Input(I),
+    // Text pasted in a terminal with bracketed paste enabled, received at once instead of key by key
+    Paste(String),
     Tick,
 }
 
+// Sequences sent by the terminal around pasted text
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+// Sequences enabling and disabling bracketed paste in the terminal
+pub const ENABLE_BRACKETED_PASTE: &str = "\x1b[?2004h";
+pub const DISABLE_BRACKETED_PASTE: &str = "\x1b[?2004l";
+
 /// A small event handler that wrap termion input and tick events. Each event
 /// type is handled in its own thread and returned to a common `Receiver`
 #[allow(dead_code)]
@@ -46,12 +56,35 @@ impl Events {
             let tx = tx.clone();
             thread::spawn(move || {
                 let stdin = io::stdin();
-                for evt in stdin.keys() {
-                    if let Ok(key) = evt {
-                        if let Err(err) = tx.send(Event::Input(key)) {
-                            eprintln!("{}", err);
-                            return;
+                // Text being pasted, the keys are collected until the end of the paste
+                let mut paste: Option<String> = None;
+                for evt in stdin.events() {
+                    let event = match evt {
+                        Ok(TermEvent::Unsupported(seq)) if seq == PASTE_START => {
+                            paste = Some(String::new());
+                            continue;
+                        }
+                        Ok(TermEvent::Unsupported(seq)) if seq == PASTE_END => {
+                            if let Some(text) = paste.take() {
+                                Event::Paste(text)
+                            } else {
+                                continue;
+                            }
+                        }
+                        Ok(TermEvent::Key(key)) => {
+                            if let Some(text) = &mut paste {
+                                if let Key::Char(c) = key {
+                                    text.push(c);
+                                }
+                                continue;
+                            }
+                            Event::Input(key)
                         }
+                        _ => continue,
+                    };
+                    if let Err(err) = tx.send(event) {
+                        eprintln!("{}", err);
+                        return;
                     }
                 }
             })
@@ -1,4 +1,9 @@
-use tui::{style::Color, widgets::ListState};
+use std::{collections::HashMap, error::Error, fs, path::Path};
+use termion::event::Key;
+use tui::{
+    style::{Color, Modifier},
+    widgets::{BorderType, Borders, ListState},
+};
 pub mod event;
 use css_color_parser::Color as CssColor;
 use serde_derive::{Deserialize, Serialize};
@@ -17,6 +22,12 @@ impl<T> StatefulList<T> {
     }
 
     pub fn next(&mut self) {
+        // Nothing can be selected in an empty list
+        if self.items.is_empty() {
+            self.unselect();
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -31,6 +42,11 @@ impl<T> StatefulList<T> {
     }
 
     pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            self.unselect();
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -55,6 +71,12 @@ pub enum AppMode {
     NormalMode,
 }
 
+// The pane that receives the motions
+pub enum Pane {
+    Explorer,
+    Editor,
+}
+
 #[allow(dead_code)]
 #[derive(Clone, Copy)]
 pub enum StatusLevel {
@@ -78,11 +100,35 @@ impl Default for Status {
     }
 }
 
+#[allow(dead_code)]
 pub enum AppEvent {
     Close,
     ShowDialog((String, String)),
+    ShowConfirm((String, String, Box<AppEvent>)),
     SetStatus(Status),
     SetWorkspace(String),
+    OpenFile(String),
+    Save(Option<String>),
+    ForceSave(String),
+    ShowWorkspace,
+    ShowLogs,
+    RefreshExplorer,
+    ShowPalette,
+    NewFromTemplate((String, String)),
+    CreatePath(String),
+    DeletePath(String),
+    RenamePath((String, String)),
+    RunInTerminal(String),
+    CloseTerminal,
+    SetOption((String, String)),
+    SetProgress(Option<Progress>),
+}
+
+// Progress of a long operation, `done` is `None` when the length of the operation is unknown
+#[derive(Clone)]
+pub struct Progress {
+    pub label: String,
+    pub done: Option<(u64, u64)>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -92,17 +138,164 @@ pub enum NodeType {
     Info = 0,
 }
 
+#[derive(Clone, Copy)]
+pub enum ExplorerSort {
+    Name,
+    Type,
+    Modified,
+}
+
+impl ExplorerSort {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(ExplorerSort::Name),
+            "type" => Some(ExplorerSort::Type),
+            "mtime" => Some(ExplorerSort::Modified),
+            _ => None,
+        }
+    }
+}
+
+// Actions of the normal mode, their keys can be changed in the `keybindings` section of the configuration
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Open,
+    Confirm,
+    Cancel,
+    Quit,
+    Close,
+    ToggleExplorer,
+    SwitchPane,
+    ShrinkExplorer,
+    GrowExplorer,
+    Refresh,
+    Palette,
+    CommandMode,
+    InsertMode,
+    Down,
+    Up,
+    Left,
+    Right,
+    ExpandNode,
+}
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "open" => Some(Action::Open),
+            "confirm" => Some(Action::Confirm),
+            "cancel" => Some(Action::Cancel),
+            "quit" => Some(Action::Quit),
+            "close" => Some(Action::Close),
+            "toggle_explorer" => Some(Action::ToggleExplorer),
+            "switch_pane" => Some(Action::SwitchPane),
+            "shrink_explorer" => Some(Action::ShrinkExplorer),
+            "grow_explorer" => Some(Action::GrowExplorer),
+            "refresh" => Some(Action::Refresh),
+            "palette" => Some(Action::Palette),
+            "command_mode" => Some(Action::CommandMode),
+            "insert_mode" => Some(Action::InsertMode),
+            "down" => Some(Action::Down),
+            "up" => Some(Action::Up),
+            "left" => Some(Action::Left),
+            "right" => Some(Action::Right),
+            "expand_node" => Some(Action::ExpandNode),
+            _ => None,
+        }
+    }
+}
+
+// The tables (`theme`, `icons`) must stay at the end, TOML can't serialize values after a table
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Config {
     pub logs_directory: Option<String>,
+    pub log_format: Option<String>,
+    pub max_log_files: Option<usize>,
+    pub status_format: Option<String>,
+    pub explorer_sort: Option<String>,
+    pub directories_first: Option<bool>,
+    pub use_icons: Option<bool>,
+    pub explorer_width: Option<u16>,
+    pub normal_mode_label: Option<String>,
+    pub insert_mode_label: Option<String>,
+    pub command_mode_label: Option<String>,
+    pub default_workspace: Option<String>,
+    pub startup_file: Option<String>,
+    pub monochrome: Option<bool>,
+    pub compact: Option<bool>,
+    pub max_path_width: Option<usize>,
+    pub leader_key: Option<char>,
     pub theme: Option<Theme>,
+    pub icons: Option<HashMap<String, String>>,
+    pub leader_bindings: Option<HashMap<String, String>>,
+    pub keybindings: Option<HashMap<String, String>>,
+}
+
+// Options a project configuration can't set, a cloned repository could bind keys to shell commands with them
+const GLOBAL_ONLY_OPTIONS: [&str; 2] = ["leader_bindings", "keybindings"];
+
+impl Config {
+    // Apply the configuration of a project over this one, only the fields it sets are replaced. The global only
+    // options it sets are ignored and their names returned
+    pub fn merge_project(&self, overrides: &str) -> Result<(Config, Vec<String>), Box<dyn Error>> {
+        let mut overrides: toml::Value = toml::from_str(overrides)?;
+        let mut ignored = Vec::new();
+        if let toml::Value::Table(table) = &mut overrides {
+            for option in GLOBAL_ONLY_OPTIONS.iter() {
+                if table.remove(*option).is_some() {
+                    ignored.push(option.to_string());
+                }
+            }
+        }
+
+        let mut base = toml::Value::try_from(self)?;
+        merge_values(&mut base, overrides);
+        Ok((base.try_into()?, ignored))
+    }
+}
+
+// Merge the tables recursively, any other value of `overrides` replaces the one in `base`
+fn merge_values(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                if let Some(current) = base.get_mut(&key) {
+                    merge_values(current, value);
+                } else {
+                    base.insert(key, value);
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
             logs_directory: Some(String::from("~/.ledit/logs")),
+            log_format: Some(String::from("text")),
+            max_log_files: Some(10),
             theme: Some(Theme::default()),
+            status_format: Some(String::from(
+                "Current Mode: {mode}    {status}    {progress}",
+            )),
+            explorer_sort: Some(String::from("name")),
+            directories_first: Some(true),
+            use_icons: Some(false),
+            explorer_width: Some(20),
+            normal_mode_label: Some(String::from("Normal Mode")),
+            insert_mode_label: Some(String::from("Insert Mode")),
+            command_mode_label: Some(String::from("Command Mode")),
+            default_workspace: None,
+            startup_file: None,
+            monochrome: Some(false),
+            compact: Some(false),
+            max_path_width: Some(40),
+            leader_key: Some(','),
+            icons: None,
+            leader_bindings: None,
+            keybindings: None,
         }
     }
 }
@@ -123,11 +316,24 @@ pub struct Theme {
     pub commands_view_background: Option<String>,
     pub commands_view_foreground: Option<String>,
     pub explorer_hidden_foreground: Option<String>,
+    pub explorer_symlink_foreground: Option<String>,
     pub app_background: Option<String>,
     pub app_foreground: Option<String>,
     pub status_error: Option<String>,
     pub status_warning: Option<String>,
     pub status_info: Option<String>,
+    pub normal_mode_background: Option<String>,
+    pub normal_mode_foreground: Option<String>,
+    pub insert_mode_background: Option<String>,
+    pub insert_mode_foreground: Option<String>,
+    pub command_mode_background: Option<String>,
+    pub command_mode_foreground: Option<String>,
+    pub dialog_border: Option<String>,
+    pub app_border_type: Option<String>,
+    pub dialog_border_type: Option<String>,
+    pub explorer_border_type: Option<String>,
+    pub editor_border_type: Option<String>,
+    pub commands_view_border_type: Option<String>,
 }
 
 impl Default for Theme {
@@ -144,49 +350,548 @@ impl Default for Theme {
             explorer_file_foreground: Some("#0000FF".to_string()),
             explorer_info_foreground: Some("#808080".to_string()),
             explorer_hidden_foreground: Some("#808080".to_string()),
+            explorer_symlink_foreground: Some("#00FFFF".to_string()),
             active_view_border: Some("#0084FF".to_string()),
             view_border: Some("#FFFFFF".to_string()),
             editor_background: Some("#000000".to_string()),
             commands_view_background: Some("#000000".to_string()),
             commands_view_foreground: Some("#FFFFFF".to_string()),
             status_info: Some("#00FF00".to_string()),
-            status_warning: Some("FF9100".to_string()),
+            status_warning: Some("#FF9100".to_string()),
             status_error: Some("#FF0000".to_string()),
+            normal_mode_background: Some("#0000ff".to_string()),
+            normal_mode_foreground: Some("#FFFFFF".to_string()),
+            insert_mode_background: Some("#0000ff".to_string()),
+            insert_mode_foreground: Some("#FFFFFF".to_string()),
+            command_mode_background: Some("#0000ff".to_string()),
+            command_mode_foreground: Some("#FFFFFF".to_string()),
+            dialog_border: Some("#FF0000".to_string()),
+            app_border_type: Some("rounded".to_string()),
+            dialog_border_type: Some("rounded".to_string()),
+            explorer_border_type: Some("plain".to_string()),
+            editor_border_type: Some("plain".to_string()),
+            commands_view_border_type: Some("plain".to_string()),
         }
     }
 }
 
 impl Theme {
+    // Copy of the theme using the default colors of the terminal everywhere
+    pub fn monochrome(&self) -> Self {
+        let reset = Some("reset".to_string());
+        Theme {
+            status_bar_background: reset.clone(),
+            status_bar_foreground: reset.clone(),
+            explorer_background: reset.clone(),
+            explorer_selected_background: reset.clone(),
+            explorer_selected_foreground: reset.clone(),
+            explorer_directory_foreground: reset.clone(),
+            explorer_file_foreground: reset.clone(),
+            explorer_info_foreground: reset.clone(),
+            active_view_border: reset.clone(),
+            view_border: reset.clone(),
+            editor_background: reset.clone(),
+            commands_view_background: reset.clone(),
+            commands_view_foreground: reset.clone(),
+            explorer_hidden_foreground: reset.clone(),
+            explorer_symlink_foreground: reset.clone(),
+            app_background: reset.clone(),
+            app_foreground: reset.clone(),
+            status_error: reset.clone(),
+            status_warning: reset.clone(),
+            status_info: reset.clone(),
+            normal_mode_background: reset.clone(),
+            normal_mode_foreground: reset.clone(),
+            insert_mode_background: reset.clone(),
+            insert_mode_foreground: reset.clone(),
+            command_mode_background: reset.clone(),
+            command_mode_foreground: reset.clone(),
+            dialog_border: reset,
+            ..self.clone()
+        }
+    }
+
     pub fn get_color_for(value: Option<String>) -> Option<Color> {
         if let Some(c) = value {
-            return Some(Color::Rgb(
-                c.parse::<CssColor>()
-                    .unwrap_or(CssColor {
-                        r: 0,
-                        g: 0,
-                        b: 0,
-                        a: 1.0,
-                    })
-                    .r,
-                c.parse::<CssColor>()
-                    .unwrap_or(CssColor {
-                        r: 0,
-                        g: 0,
-                        b: 0,
-                        a: 1.0,
-                    })
-                    .g,
-                c.parse::<CssColor>()
-                    .unwrap_or(CssColor {
-                        r: 0,
-                        g: 0,
-                        b: 0,
-                        a: 1.0,
-                    })
-                    .b,
-            ));
+            // `reset` is the default color of the terminal
+            if c == "reset" {
+                return Some(Color::Reset);
+            }
+            // Colors that can't be parsed are black
+            let color = c.parse::<CssColor>().unwrap_or(CssColor {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 1.0,
+            });
+            return Some(Color::Rgb(color.r, color.g, color.b));
         } else {
             return None;
         }
     }
+
+    // Names of the colors of the theme that can't be parsed
+    pub fn invalid_colors(&self) -> Vec<String> {
+        let mut invalid = Vec::new();
+        if let Ok(toml::Value::Table(fields)) = toml::Value::try_from(self) {
+            for (name, value) in fields.iter() {
+                if name.ends_with("_border_type") {
+                    continue;
+                }
+                if let Some(c) = value.as_str() {
+                    if c != "reset" && c.parse::<CssColor>().is_err() {
+                        invalid.push(format!("{} = \"{}\"", name, c));
+                    }
+                }
+            }
+        }
+        invalid
+    }
+
+    // Get the borders of a pane from the border type name, `none` hides all the borders
+    pub fn get_borders_for(value: Option<String>, borders: Borders) -> (Borders, BorderType) {
+        match value.as_deref() {
+            Some("none") => (Borders::NONE, BorderType::Plain),
+            Some("rounded") => (borders, BorderType::Rounded),
+            Some("double") => (borders, BorderType::Double),
+            Some("thick") => (borders, BorderType::Thick),
+            _ => (borders, BorderType::Plain),
+        }
+    }
+}
+
+// Colors and borders of the theme converted once when the configuration is loaded, so drawing doesn't parse them
+pub struct ResolvedTheme {
+    pub status_bar_background: Color,
+    pub status_bar_foreground: Color,
+    pub explorer_background: Color,
+    pub explorer_selected_background: Color,
+    pub explorer_selected_foreground: Color,
+    pub explorer_directory_foreground: Color,
+    pub explorer_file_foreground: Color,
+    pub explorer_info_foreground: Color,
+    pub active_view_border: Color,
+    pub view_border: Color,
+    pub editor_background: Color,
+    pub commands_view_background: Color,
+    pub commands_view_foreground: Color,
+    pub explorer_hidden_foreground: Color,
+    pub explorer_symlink_foreground: Color,
+    pub app_background: Color,
+    pub app_foreground: Color,
+    pub status_error: Color,
+    pub status_warning: Color,
+    pub status_info: Color,
+    pub normal_mode_background: Color,
+    pub normal_mode_foreground: Color,
+    pub insert_mode_background: Color,
+    pub insert_mode_foreground: Color,
+    pub command_mode_background: Color,
+    pub command_mode_foreground: Color,
+    pub dialog_border: Color,
+    pub app_borders: (Borders, BorderType),
+    pub dialog_borders: (Borders, BorderType),
+    pub explorer_borders: (Borders, BorderType),
+    pub editor_borders: (Borders, BorderType),
+    pub commands_view_borders: (Borders, BorderType),
+    // Added to the selected entries, set when the colors alone can't show the selection
+    pub selected_modifier: Modifier,
+}
+
+impl ResolvedTheme {
+    pub fn new(theme: Option<Theme>) -> Self {
+        let theme = theme.unwrap_or_default();
+        let default = Theme::default();
+        // Fields missing in the theme use the default colors
+        let color = |value: &Option<String>, fallback: &Option<String>| {
+            Theme::get_color_for(value.clone().or_else(|| fallback.clone())).unwrap()
+        };
+        let borders = |value: &Option<String>, fallback: &Option<String>, borders: Borders| {
+            Theme::get_borders_for(value.clone().or_else(|| fallback.clone()), borders)
+        };
+
+        ResolvedTheme {
+            status_bar_background: color(
+                &theme.status_bar_background,
+                &default.status_bar_background,
+            ),
+            status_bar_foreground: color(
+                &theme.status_bar_foreground,
+                &default.status_bar_foreground,
+            ),
+            explorer_background: color(&theme.explorer_background, &default.explorer_background),
+            explorer_selected_background: color(
+                &theme.explorer_selected_background,
+                &default.explorer_selected_background,
+            ),
+            explorer_selected_foreground: color(
+                &theme.explorer_selected_foreground,
+                &default.explorer_selected_foreground,
+            ),
+            explorer_directory_foreground: color(
+                &theme.explorer_directory_foreground,
+                &default.explorer_directory_foreground,
+            ),
+            explorer_file_foreground: color(
+                &theme.explorer_file_foreground,
+                &default.explorer_file_foreground,
+            ),
+            explorer_info_foreground: color(
+                &theme.explorer_info_foreground,
+                &default.explorer_info_foreground,
+            ),
+            active_view_border: color(&theme.active_view_border, &default.active_view_border),
+            view_border: color(&theme.view_border, &default.view_border),
+            editor_background: color(&theme.editor_background, &default.editor_background),
+            commands_view_background: color(
+                &theme.commands_view_background,
+                &default.commands_view_background,
+            ),
+            commands_view_foreground: color(
+                &theme.commands_view_foreground,
+                &default.commands_view_foreground,
+            ),
+            explorer_hidden_foreground: color(
+                &theme.explorer_hidden_foreground,
+                &default.explorer_hidden_foreground,
+            ),
+            explorer_symlink_foreground: color(
+                &theme.explorer_symlink_foreground,
+                &default.explorer_symlink_foreground,
+            ),
+            app_background: color(&theme.app_background, &default.app_background),
+            app_foreground: color(&theme.app_foreground, &default.app_foreground),
+            status_error: color(&theme.status_error, &default.status_error),
+            status_warning: color(&theme.status_warning, &default.status_warning),
+            status_info: color(&theme.status_info, &default.status_info),
+            normal_mode_background: color(
+                &theme.normal_mode_background,
+                &default.normal_mode_background,
+            ),
+            normal_mode_foreground: color(
+                &theme.normal_mode_foreground,
+                &default.normal_mode_foreground,
+            ),
+            insert_mode_background: color(
+                &theme.insert_mode_background,
+                &default.insert_mode_background,
+            ),
+            insert_mode_foreground: color(
+                &theme.insert_mode_foreground,
+                &default.insert_mode_foreground,
+            ),
+            command_mode_background: color(
+                &theme.command_mode_background,
+                &default.command_mode_background,
+            ),
+            command_mode_foreground: color(
+                &theme.command_mode_foreground,
+                &default.command_mode_foreground,
+            ),
+            dialog_border: color(&theme.dialog_border, &default.dialog_border),
+            app_borders: borders(
+                &theme.app_border_type,
+                &default.app_border_type,
+                Borders::TOP | Borders::BOTTOM,
+            ),
+            dialog_borders: borders(
+                &theme.dialog_border_type,
+                &default.dialog_border_type,
+                Borders::ALL,
+            ),
+            explorer_borders: borders(
+                &theme.explorer_border_type,
+                &default.explorer_border_type,
+                Borders::ALL,
+            ),
+            editor_borders: borders(
+                &theme.editor_border_type,
+                &default.editor_border_type,
+                Borders::ALL,
+            ),
+            commands_view_borders: borders(
+                &theme.commands_view_border_type,
+                &default.commands_view_border_type,
+                Borders::ALL,
+            ),
+            selected_modifier: Modifier::empty(),
+        }
+    }
+}
+
+// Shorten a path for display, the home directory becomes `~` and the middle components are replaced
+// with `…` until the path fits in `max_width` characters
+pub fn shorten_path(path: &Path, max_width: usize) -> String {
+    let home = shellexpand::tilde("~").to_string();
+    let display = match path.strip_prefix(&home) {
+        Ok(rest) if home != "~" => Path::new("~").join(rest).to_string_lossy().to_string(),
+        _ => path.to_string_lossy().to_string(),
+    };
+
+    let parts: Vec<&str> = display.split('/').collect();
+    if display.chars().count() <= max_width || parts.len() <= 3 {
+        return display;
+    }
+
+    // Keep the first two components and as many of the last ones as possible
+    let head = parts[..2].join("/");
+    for start in 3..parts.len() {
+        let shortened = format!("{}/…/{}", head, parts[start..].join("/"));
+        if shortened.chars().count() <= max_width {
+            return shortened;
+        }
+    }
+    format!("{}/…/{}", head, parts[parts.len() - 1])
+}
+
+// Get the name of the checked out git branch of the given directory, or the short commit hash if the HEAD is detached
+pub fn git_branch(path: &Path) -> Option<String> {
+    let head = fs::read_to_string(path.join(".git").join("HEAD")).ok()?;
+    let head = head.trim();
+
+    if let Some(branch) = head.strip_prefix("ref: refs/heads/") {
+        Some(branch.to_string())
+    } else {
+        Some(head.chars().take(7).collect())
+    }
+}
+
+// Parse the value of an on/off option
+pub fn parse_switch(value: &str) -> Option<bool> {
+    match value {
+        "on" | "true" | "yes" => Some(true),
+        "off" | "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+// Keys of the normal mode actions when they are not set in the configuration
+pub fn default_keybindings() -> Vec<(&'static str, &'static str)> {
+    vec![
+        ("open", "enter"),
+        ("confirm", "y"),
+        ("cancel", "n"),
+        ("quit", "q"),
+        ("close", "esc"),
+        ("toggle_explorer", "f"),
+        ("switch_pane", "tab"),
+        ("shrink_explorer", "<"),
+        ("grow_explorer", ">"),
+        ("refresh", "r"),
+        ("palette", "ctrl-p"),
+        ("command_mode", "c"),
+        ("insert_mode", "i"),
+        ("down", "down"),
+        ("up", "up"),
+        ("left", "left"),
+        ("right", "right"),
+        ("expand_node", "space"),
+    ]
+}
+
+// Parse a key of the configuration, like `q`, `space`, `ctrl-p`, `alt-x` or `f5`
+pub fn parse_key(name: &str) -> Option<Key> {
+    // A single char is the key itself
+    let single = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+    if let Some(c) = single(name) {
+        return Some(Key::Char(c));
+    }
+    // Strip a prefix ignoring its case, on the name itself so that the rest keeps its chars
+    let strip = |prefix: &str| match name.get(..prefix.len()) {
+        Some(p) if p.eq_ignore_ascii_case(prefix) => Some(&name[prefix.len()..]),
+        _ => None,
+    };
+
+    match name.to_lowercase().as_str() {
+        "enter" => Some(Key::Char('\n')),
+        "tab" => Some(Key::Char('\t')),
+        "space" => Some(Key::Char(' ')),
+        "esc" => Some(Key::Esc),
+        "backspace" => Some(Key::Backspace),
+        "delete" => Some(Key::Delete),
+        "up" => Some(Key::Up),
+        "down" => Some(Key::Down),
+        "left" => Some(Key::Left),
+        "right" => Some(Key::Right),
+        "home" => Some(Key::Home),
+        "end" => Some(Key::End),
+        "pageup" => Some(Key::PageUp),
+        "pagedown" => Some(Key::PageDown),
+        _ => {
+            if let Some(rest) = strip("ctrl-") {
+                single(rest).map(|c| Key::Ctrl(c.to_ascii_lowercase()))
+            } else if let Some(rest) = strip("alt-") {
+                single(rest).map(Key::Alt)
+            } else if let Some(number) = strip("f") {
+                match number.parse::<u8>() {
+                    Ok(n) if (1..=12).contains(&n) => Some(Key::F(n)),
+                    _ => None,
+                }
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// Name of a key shown to the user, the opposite of `parse_key`
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Char('\n') => "Enter".to_string(),
+        Key::Char('\t') => "Tab".to_string(),
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("Ctrl-{}", c),
+        Key::Alt(c) => format!("Alt-{}", c),
+        Key::F(n) => format!("F{}", n),
+        Key::Esc => "Esc".to_string(),
+        Key::Backspace => "Backspace".to_string(),
+        Key::Delete => "Delete".to_string(),
+        Key::Up => "Up".to_string(),
+        Key::Down => "Down".to_string(),
+        Key::Left => "Left".to_string(),
+        Key::Right => "Right".to_string(),
+        Key::Home => "Home".to_string(),
+        Key::End => "End".to_string(),
+        Key::PageUp => "PageUp".to_string(),
+        Key::PageDown => "PageDown".to_string(),
+        _ => "?".to_string(),
+    }
+}
+
+// Default Nerd Font icon for a file extension or for one of the `directory`, `directory_open` and `file` keys
+pub fn default_icon(key: &str) -> Option<&'static str> {
+    match key {
+        "directory" => Some("\u{f07b}"),
+        "directory_open" => Some("\u{f07c}"),
+        "file" => Some("\u{f15b}"),
+        "rs" => Some("\u{e7a8}"),
+        "toml" => Some("\u{e615}"),
+        "md" => Some("\u{e609}"),
+        "json" => Some("\u{e60b}"),
+        "txt" => Some("\u{f15c}"),
+        "lock" => Some("\u{f023}"),
+        "sh" => Some("\u{f489}"),
+        "py" => Some("\u{e606}"),
+        "js" => Some("\u{e74e}"),
+        "ts" => Some("\u{e628}"),
+        "html" => Some("\u{e736}"),
+        "css" => Some("\u{e749}"),
+        "c" => Some("\u{e61e}"),
+        "h" => Some("\u{e61e}"),
+        "cpp" => Some("\u{e61d}"),
+        "go" => Some("\u{e626}"),
+        "java" => Some("\u{e738}"),
+        "png" | "jpg" | "jpeg" | "gif" | "svg" => Some("\u{f1c5}"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_in_small_lists() {
+        let mut list: StatefulList<u8> = StatefulList::new();
+        list.next();
+        assert_eq!(list.state.selected(), None);
+        list.previous();
+        assert_eq!(list.state.selected(), None);
+
+        // A selection left over from a list that has been emptied is cleared
+        list.state.select(Some(3));
+        list.next();
+        assert_eq!(list.state.selected(), None);
+
+        list.items.push(1);
+        list.next();
+        assert_eq!(list.state.selected(), Some(0));
+        list.next();
+        assert_eq!(list.state.selected(), Some(0));
+        list.previous();
+        assert_eq!(list.state.selected(), Some(0));
+    }
+
+    #[test]
+    fn parse_keys() {
+        // Single chars keep their case
+        assert_eq!(parse_key("q"), Some(Key::Char('q')));
+        assert_eq!(parse_key("Q"), Some(Key::Char('Q')));
+        assert_eq!(parse_key("<"), Some(Key::Char('<')));
+        assert_eq!(parse_key("é"), Some(Key::Char('é')));
+
+        assert_eq!(parse_key("enter"), Some(Key::Char('\n')));
+        assert_eq!(parse_key("Tab"), Some(Key::Char('\t')));
+        assert_eq!(parse_key("SPACE"), Some(Key::Char(' ')));
+        assert_eq!(parse_key("esc"), Some(Key::Esc));
+        assert_eq!(parse_key("backspace"), Some(Key::Backspace));
+        assert_eq!(parse_key("delete"), Some(Key::Delete));
+        assert_eq!(parse_key("up"), Some(Key::Up));
+        assert_eq!(parse_key("down"), Some(Key::Down));
+        assert_eq!(parse_key("left"), Some(Key::Left));
+        assert_eq!(parse_key("right"), Some(Key::Right));
+        assert_eq!(parse_key("home"), Some(Key::Home));
+        assert_eq!(parse_key("end"), Some(Key::End));
+        assert_eq!(parse_key("pageup"), Some(Key::PageUp));
+        assert_eq!(parse_key("pagedown"), Some(Key::PageDown));
+
+        assert_eq!(parse_key("ctrl-p"), Some(Key::Ctrl('p')));
+        assert_eq!(parse_key("Ctrl-P"), Some(Key::Ctrl('p')));
+        assert_eq!(parse_key("alt-x"), Some(Key::Alt('x')));
+        assert_eq!(parse_key("ALT-X"), Some(Key::Alt('X')));
+        assert_eq!(parse_key("alt-é"), Some(Key::Alt('é')));
+
+        for n in 1..=12 {
+            assert_eq!(parse_key(&format!("f{}", n)), Some(Key::F(n)));
+        }
+        assert_eq!(parse_key("F5"), Some(Key::F(5)));
+    }
+
+    #[test]
+    fn reject_invalid_keys() {
+        assert_eq!(parse_key(""), None);
+        assert_eq!(parse_key("ctl-q"), None);
+        assert_eq!(parse_key("ctrl-"), None);
+        assert_eq!(parse_key("ctrl-pp"), None);
+        assert_eq!(parse_key("alt-"), None);
+        assert_eq!(parse_key("f0"), None);
+        assert_eq!(parse_key("f13"), None);
+        assert_eq!(parse_key("fx"), None);
+        assert_eq!(parse_key("enterr"), None);
+        // Multibyte names must not be sliced in the middle of a char
+        assert_eq!(parse_key("éé"), None);
+        assert_eq!(parse_key("alé-x"), None);
+        assert_eq!(parse_key("ctrlé"), None);
+    }
+
+    #[test]
+    fn shorten_paths() {
+        // Paths that fit or have too few components are kept
+        assert_eq!(shorten_path(Path::new("/a/b"), 80), "/a/b");
+        assert_eq!(
+            shorten_path(Path::new("/averyveryverylongname/x"), 5),
+            "/averyveryverylongname/x"
+        );
+
+        // The middle components are replaced, keeping as many of the last ones as possible
+        let path = Path::new("/usr/local/share/very/long/name");
+        assert_eq!(shorten_path(path, 80), "/usr/local/share/very/long/name");
+        assert_eq!(shorten_path(path, 20), "/usr/…/long/name");
+        assert_eq!(shorten_path(path, 5), "/usr/…/name");
+
+        // The home directory is shown as ~
+        let home = shellexpand::tilde("~").to_string();
+        if home != "~" {
+            let path = Path::new(&home).join("projects/ledit/src/util/mod.rs");
+            assert_eq!(shorten_path(&path, 80), "~/projects/ledit/src/util/mod.rs");
+            assert_eq!(shorten_path(&path, 20), "~/projects/…/mod.rs");
+        }
+    }
 }